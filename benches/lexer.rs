@@ -0,0 +1,62 @@
+//! Measures how scanning and compiling time grow with template size. Run with
+//! `cargo bench --bench lexer`; the throughput should stay roughly constant
+//! as the templates double in size.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use stache::lexer::Lexer;
+use stache::Template;
+
+const MB: usize = 1024 * 1024;
+
+/// Builds a template of at least `size` bytes mixing tags, long runs of text
+/// and a delimiter change.
+fn template(size: usize) -> String {
+    let chunk = "\
+{{#items}}
+  {{name}} <{{{email}}}> {{& note}}
+  Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor.
+  Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.
+  {{! a comment }}
+{{/items}}
+{{=<% %>=}}<%greeting%>, {{ left alone }}<%={{ }}=%>
+";
+    chunk.repeat(size / chunk.len() + 1)
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let runs = 5;
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+    start.elapsed() / runs
+}
+
+fn report(name: &str, mut f: impl FnMut(&str)) {
+    println!("{name}");
+    println!("{:>8} {:>12} {:>12}", "size", "time", "throughput");
+    for size in [MB, 2 * MB, 4 * MB, 8 * MB] {
+        let text = template(size);
+        let elapsed = time(|| f(&text));
+        let throughput = text.len() as f64 / MB as f64 / elapsed.as_secs_f64();
+        println!(
+            "{:>6}MB {:>12.2?} {:>8.1}MB/s",
+            size / MB,
+            elapsed,
+            throughput
+        );
+    }
+}
+
+fn main() {
+    report("scan", |text| {
+        for token in Lexer::new(text).tokens() {
+            black_box(token.unwrap());
+        }
+    });
+    report("compile", |text| {
+        black_box(Template::compile(text).unwrap());
+    });
+}
@@ -0,0 +1,148 @@
+//! Measures rendering with the tree-walking and program backends. Run with
+//! `cargo bench --bench render`. The time per rendered item should stay
+//! roughly constant as lists nest more deeply, and the program backend should
+//! render the same templates faster.
+//!
+//! Contexts are passed to a render by value, so building them is timed
+//! separately and left out of the reported times.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use stache::{Backend, CompileOptions, Context, MapBuilder, RenderOptions, Template};
+
+/// Number of items in the innermost list of the nested lists.
+const WIDTH: usize = 2_000;
+
+/// Builds a template with `depth` nested `items` sections, each rendering
+/// two variables ahead of the next level.
+fn nested_template(depth: usize) -> String {
+    let open = "{{#items}}<{{name}}:{{index}}>";
+    let close = "{{/items}}";
+    format!("{}{}", open.repeat(depth), close.repeat(depth))
+}
+
+/// Builds a context nesting `depth` lists, where the innermost list holds
+/// `WIDTH` items and every other list a single item, so the number of
+/// rendered items grows linearly with the depth.
+fn nested_context(depth: usize) -> Context {
+    let mut items: Vec<Context> = (0..WIDTH as i64)
+        .map(|index| {
+            MapBuilder::new()
+                .str("name", "leaf")
+                .int("index", index)
+                .build()
+        })
+        .collect();
+    for level in 1..depth {
+        items = vec![MapBuilder::new()
+            .str("name", "level")
+            .int("index", level as i64)
+            .list("items", items)
+            .build()];
+    }
+    MapBuilder::new().list("items", items).build()
+}
+
+/// A table of rows, typical of pages rendered by a web service.
+const ROWS_TEMPLATE: &str = "\
+<table>
+  {{#rows}}
+  <tr class=\"{{#admin}}admin{{/admin}}{{^admin}}user{{/admin}}\">
+    <td>{{id}}</td>
+    <td>{{name}}</td>
+    <td>{{user.email}}</td>
+    <td>{{{note}}}</td>
+  </tr>
+  {{/rows}}
+  {{^rows}}<tr><td>No rows</td></tr>{{/rows}}
+</table>
+";
+
+fn rows_context(count: usize) -> Context {
+    let rows = (0..count)
+        .map(|id| {
+            let user = MapBuilder::new()
+                .str("email", "someone@example.com")
+                .build();
+            let Context::Map(user) = user else {
+                unreachable!();
+            };
+            MapBuilder::new()
+                .int("id", id as i64)
+                .str("name", "Tom & Jerry <cartoon>")
+                .bool("admin", id % 10 == 0)
+                .map("user", user)
+                .str("note", "<em>note</em>")
+                .build()
+        })
+        .collect();
+    MapBuilder::new().list("rows", rows).build()
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let runs = 5;
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+    start.elapsed() / runs
+}
+
+/// Times rendering a template with the given backend, excluding the time
+/// taken to build its context.
+fn render_time(
+    text: &str,
+    backend: Backend,
+    context: impl Fn() -> Context,
+    options: RenderOptions,
+) -> Duration {
+    let compile_options = CompileOptions::new().backend(backend);
+    let template = Template::compile_with_options(text, compile_options).unwrap();
+    let total = time(|| {
+        let rendered = template.render_with_options(context(), Default::default(), options.clone());
+        black_box(rendered).unwrap();
+    });
+    let build = time(|| {
+        black_box(context());
+    });
+    total.saturating_sub(build)
+}
+
+fn main() {
+    println!("nested lists");
+    println!(
+        "{:>8} {:>12} {:>12} {:>12} {:>12}",
+        "depth", "tree", "per item", "program", "per item"
+    );
+    for depth in [100, 200, 400, 800] {
+        let text = nested_template(depth);
+        let items = (WIDTH + depth - 1) as u32;
+        let options = RenderOptions::new().max_depth(depth + 1);
+        let context = || nested_context(depth);
+        let tree = render_time(&text, Backend::Tree, context, options.clone());
+        let program = render_time(&text, Backend::Program, context, options);
+        println!(
+            "{:>8} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.2?}",
+            depth,
+            tree,
+            tree / items,
+            program,
+            program / items
+        );
+    }
+
+    println!("rows");
+    println!("{:>8} {:>12} {:>12}", "rows", "tree", "program");
+    for count in [10_000, 20_000, 40_000, 80_000] {
+        let context = || rows_context(count);
+        let tree = render_time(ROWS_TEMPLATE, Backend::Tree, context, RenderOptions::new());
+        let program = render_time(
+            ROWS_TEMPLATE,
+            Backend::Program,
+            context,
+            RenderOptions::new(),
+        );
+        println!("{:>8} {:>12.2?} {:>12.2?}", count, tree, program);
+    }
+}
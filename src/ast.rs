@@ -1,28 +1,47 @@
+//! The syntax tree of a compiled template.
+//!
+//! Nodes borrow names and text from the template source. Comments and
+//! set-delimiter tags do not appear in the tree, and whitespace around
+//! standalone tags has already been removed.
+
+use std::any::Any;
+use std::fmt;
 use std::ops::Index;
+use std::sync::Arc;
 
 #[derive(Debug, PartialEq)]
 pub enum Node<'t> {
     Root(Root<'t>),
+    /// A section or inverted section, such as `{{#name}}...{{/name}}`.
     Section(Section<'t>),
+    /// An interpolated value, such as `{{name}}` or `{{{name}}}`.
     Variable(Variable<'t>),
+    /// A partial, such as `{{>name}}`.
     Partial(Partial<'t>),
-    Text(&'t str),
+    /// Literal text, including whitespace and newlines.
+    Text(Text<'t>),
+    /// A tag whose sigil an extension registered, such as `{{@icon star}}`.
+    Extension(Extension<'t>),
 }
 
 impl<'t> Node<'t> {
-    pub fn children(&self) -> &Vec<Node<'t>> {
+    /// Returns the children of a root or section, or an empty slice for nodes
+    /// that cannot have children.
+    pub fn children(&self) -> &[Node<'t>] {
         match self {
             Node::Root(x) => &x.children,
             Node::Section(x) => &x.children,
-            _ => panic!("node does not have children"),
+            _ => &[],
         }
     }
 
-    pub fn push(&mut self, child: Node<'t>) {
+    /// Returns the children of a root or section, or `None` for nodes that
+    /// cannot have children.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Node<'t>>> {
         match self {
-            Node::Root(x) => x.children.push(child),
-            Node::Section(x) => x.children.push(child),
-            _ => panic!("node does not have children"),
+            Node::Root(x) => Some(&mut x.children),
+            Node::Section(x) => Some(&mut x.children),
+            _ => None,
         }
     }
 }
@@ -38,8 +57,37 @@ impl<'t> Index<usize> for Node<'t> {
 #[derive(Debug, PartialEq, Default)]
 pub struct Root<'t> {
     pub children: Vec<Node<'t>>,
+    /// The pragmas at the top of the template, in order.
+    pub pragmas: Vec<Pragma<'t>>,
+}
+
+/// A pragma tag, such as `{{%STRICT sections=true}}`, which switches engine
+/// behavior for the template it appears at the top of.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pragma<'t> {
+    pub name: &'t str,
+    /// The options written as `key=value` after the name.
+    pub options: Vec<(&'t str, &'t str)>,
+    pub span: Span,
 }
 
+impl<'t> Pragma<'t> {
+    /// Returns the value of an option, if it is given.
+    pub fn option(&self, key: &str) -> Option<&'t str> {
+        self.options
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| *value)
+    }
+
+    /// Whether the pragma switches its behavior on, which it does unless it
+    /// is written with `enabled=false`.
+    pub fn enabled(&self) -> bool {
+        self.option("enabled") != Some("false")
+    }
+}
+
+/// Whether a section renders when its value is truthy or when it is falsy.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Variant {
     Direct,
@@ -48,41 +96,306 @@ pub enum Variant {
 
 #[derive(Debug, PartialEq)]
 pub struct Section<'t> {
-    pub name: &'t str,
+    pub name: Name<'t>,
     pub variant: Variant,
     pub children: Vec<Node<'t>>,
+    /// The location of the opening tag.
+    pub open: Span,
+    /// The location of the closing tag.
+    pub close: Span,
+    /// The arguments of a section that calls a helper, such as
+    /// `status "paid"` in `{{#if_eq status "paid"}}`. Empty for other
+    /// sections.
+    pub args: Vec<Argument<'t>>,
 }
 
 impl<'t> Section<'t> {
-    pub fn new(name: &'t str, variant: Variant) -> Self {
+    pub fn new(name: Name<'t>, variant: Variant, open: Span) -> Self {
         Self {
             name,
             variant,
             children: Vec::new(),
+            open,
+            close: Span::default(),
+            args: Vec::new(),
         }
     }
 }
 
+/// An argument passed to a helper, such as `status`, `"paid"` or
+/// `key="name"`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Argument<'t> {
+    /// The key of a named argument, written as `key=value`.
+    pub key: Option<&'t str>,
+    pub value: ArgumentValue<'t>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ArgumentValue<'t> {
+    /// A name, looked up in the context when the section renders.
+    Name(Name<'t>),
+    /// A string written in double quotes, without the quotes.
+    String(&'t str),
+    /// A number, such as `3` or `-1.5`.
+    Number(&'t str),
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Variable<'t> {
-    pub name: &'t str,
+    pub name: Name<'t>,
+    /// Whether the value is HTML-escaped, which is the case unless the tag is
+    /// written as `{{{name}}}` or `{{&name}}`.
     pub escaped: bool,
+    /// Filters the value is passed through, in order, before it is escaped.
+    pub filters: Vec<Filter<'t>>,
+    pub span: Span,
 }
 
 impl<'t> Variable<'t> {
-    pub fn new(name: &'t str, escaped: bool) -> Self {
-        Self { name, escaped }
+    pub fn new(name: Name<'t>, escaped: bool, span: Span) -> Self {
+        Self {
+            name,
+            escaped,
+            filters: Vec::new(),
+            span,
+        }
+    }
+}
+
+/// A filter applied to a variable, such as `truncate 20` in
+/// `{{title | truncate 20}}`. Arguments are separated by whitespace and may be
+/// quoted with `"` to include whitespace or `|`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Filter<'t> {
+    pub name: &'t str,
+    pub args: Vec<&'t str>,
+}
+
+/// A tag whose sigil an extension registered, holding the node the extension
+/// parsed its content into.
+#[derive(Debug, Clone)]
+pub struct Extension<'t> {
+    pub sigil: char,
+    /// The content of the tag after the sigil, without surrounding whitespace.
+    pub content: &'t str,
+    pub node: Arc<dyn Any + Send + Sync>,
+    pub span: Span,
+}
+
+/// Extension tags are equal if they are written the same, as the nodes the
+/// extensions parse cannot be compared.
+impl PartialEq for Extension<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sigil == other.sigil && self.content == other.content && self.span == other.span
+    }
+}
+
+/// A name looked up in the context by a variable or section, split into its
+/// keys when the template is compiled.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Name<'t> {
+    /// `.`, the innermost context itself.
+    Implicit,
+    /// A single key, such as `name`, looked up in each enclosing context in
+    /// turn.
+    Key(&'t str),
+    /// A dotted name, such as `person.name`. The first key is looked up like a
+    /// single key and each following key in the value found so far.
+    Path(Vec<&'t str>),
+    /// A name prefixed with `../` once for each context to skip, such as
+    /// `../name`, looked up as if the innermost contexts were not there.
+    /// `..` alone stands for `../.`.
+    Parent(usize, Box<Name<'t>>),
+    /// A name prefixed with `@root`, such as `@root.name`, looked up in the
+    /// context passed to the render only. `@root` alone is that context.
+    Root(Box<Name<'t>>),
+    /// A name prefixed with a single `.` under the `ANCHORED-DOT` pragma, such
+    /// as `.name`, looked up in the innermost context only rather than in
+    /// each enclosing context in turn.
+    Anchored(Box<Name<'t>>),
+}
+
+impl<'t> Name<'t> {
+    /// Splits a name as written in a tag, returning `None` if a dotted name
+    /// has an empty key, as in `a..b` or `.a`.
+    pub fn parse(name: &'t str) -> Option<Self> {
+        match name.strip_prefix("@root") {
+            Some("") => return Some(Name::Root(Box::new(Name::Implicit))),
+            Some(".") => return None,
+            Some(rest) if rest.starts_with('.') => {
+                let name = Self::parse_unscoped(&rest[1..])?;
+                return Some(Name::Root(Box::new(name)));
+            }
+            _ => (),
+        }
+        let mut levels = 0;
+        let mut rest = name;
+        while let Some(after) = rest.strip_prefix("../") {
+            levels += 1;
+            rest = after;
+        }
+        let name = match rest {
+            ".." => {
+                levels += 1;
+                Name::Implicit
+            }
+            _ if levels == 0 => return Self::parse_unscoped(rest),
+            "" => return None,
+            _ => Self::parse_unscoped(rest)?,
+        };
+        Some(Name::Parent(levels, Box::new(name)))
+    }
+
+    /// Splits a name as `parse` does, except that a name starting with a
+    /// single `.`, as in `.name` or `.a.b`, is anchored to the innermost
+    /// context.
+    pub fn parse_anchored(name: &'t str) -> Option<Self> {
+        match name.strip_prefix('.') {
+            Some(rest) if !rest.is_empty() && !rest.starts_with(['.', '/']) => {
+                let name = Self::parse_unscoped(rest)?;
+                Some(Name::Anchored(Box::new(name)))
+            }
+            _ => Self::parse(name),
+        }
+    }
+
+    fn parse_unscoped(name: &'t str) -> Option<Self> {
+        if name == "." {
+            return Some(Name::Implicit);
+        }
+        if !name.contains('.') {
+            return Some(Name::Key(name));
+        }
+        let keys: Vec<&str> = name.split('.').collect();
+        if keys.iter().any(|key| key.is_empty()) {
+            return None;
+        }
+        Some(Name::Path(keys))
+    }
+}
+
+impl PartialEq<&str> for Name<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        match self {
+            Name::Implicit => *other == ".",
+            Name::Key(key) => key == other,
+            Name::Path(keys) => other.split('.').eq(keys.iter().copied()),
+            Name::Parent(..) | Name::Root(_) | Name::Anchored(_) => {
+                Name::parse_anchored(other).as_ref() == Some(self)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Name::Implicit => write!(f, "."),
+            Name::Key(key) => write!(f, "{key}"),
+            Name::Path(keys) => write!(f, "{}", keys.join(".")),
+            Name::Parent(levels, name) if **name == Name::Implicit => {
+                write!(f, "{}..", "../".repeat(levels - 1))
+            }
+            Name::Parent(levels, name) => write!(f, "{}{name}", "../".repeat(*levels)),
+            Name::Root(name) if **name == Name::Implicit => write!(f, "@root"),
+            Name::Root(name) => write!(f, "@root.{name}"),
+            Name::Anchored(name) => write!(f, ".{name}"),
+        }
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Partial<'t> {
     pub name: &'t str,
+    /// For `{{>*name}}`, the name looked up to find the partial to render.
+    pub dynamic: Option<Name<'t>>,
+    /// Whitespace preceding a standalone partial tag, which is added to the
+    /// start of every line of the partial.
     pub indent: String,
+    pub span: Span,
 }
 
 impl<'t> Partial<'t> {
-    pub fn new(name: &'t str, indent: String) -> Self {
-        Self { name, indent }
+    pub fn new(name: &'t str, indent: String, span: Span) -> Self {
+        Self {
+            name,
+            dynamic: None,
+            indent,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Text<'t> {
+    pub text: &'t str,
+    pub span: Span,
+}
+
+impl<'t> Text<'t> {
+    pub fn new(text: &'t str, span: Span) -> Self {
+        Self { text, span }
+    }
+}
+
+/// A range of bytes in the template source. Spans of tags cover the whole tag
+/// including its delimiters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// A line and column in the template source, both starting at one. Columns
+/// count characters rather than bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Finds the position of a byte offset within the given source.
+    pub fn locate(text: &str, offset: usize) -> Self {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// The offsets at which the lines of a source start, for locating many
+/// offsets in the same source without scanning it again for each.
+pub(crate) struct LineIndex<'t> {
+    text: &'t str,
+    starts: Vec<usize>,
+}
+
+impl<'t> LineIndex<'t> {
+    pub fn new(text: &'t str) -> Self {
+        let newlines = text.match_indices('\n').map(|(pos, _)| pos + 1);
+        Self {
+            text,
+            starts: std::iter::once(0).chain(newlines).collect(),
+        }
+    }
+
+    /// Finds the position of a byte offset, as `Position::locate` does.
+    pub fn locate(&self, offset: usize) -> Position {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let start = self.starts[line - 1];
+        Position {
+            line,
+            column: self.text[start..offset].chars().count() + 1,
+        }
     }
 }
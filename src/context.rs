@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+#[derive(Clone)]
 pub enum Context {
     String(String),
     Integer(i64),
@@ -11,7 +12,7 @@ pub enum Context {
 }
 
 impl Context {
-    pub(crate) fn to_string(&self) -> String {
+    pub(crate) fn to_text(&self) -> String {
         match self {
             Context::String(x) => x.to_string(),
             Context::Integer(x) => x.to_string(),
@@ -28,7 +29,7 @@ impl Context {
             Context::Bool(x) => *x,
             Context::Null => false,
             Context::Map(_) => true,
-            Context::List(x) => x.len() > 0,
+            Context::List(x) => !x.is_empty(),
         }
     }
 
@@ -50,33 +51,33 @@ impl MapBuilder {
         MapBuilder::default()
     }
 
-    pub fn str<'a>(mut self, key: &str, value: &str) -> Self {
+    pub fn str(mut self, key: &str, value: &str) -> Self {
         self.context
             .insert(key.into(), Context::String(value.into()));
         self
     }
 
-    pub fn int<'a>(mut self, key: &str, value: i64) -> Self {
+    pub fn int(mut self, key: &str, value: i64) -> Self {
         self.context.insert(key.into(), Context::Integer(value));
         self
     }
 
-    pub fn float<'a>(mut self, key: &str, value: f64) -> Self {
+    pub fn float(mut self, key: &str, value: f64) -> Self {
         self.context.insert(key.into(), Context::Float(value));
         self
     }
 
-    pub fn bool<'a>(mut self, key: &str, value: bool) -> Self {
+    pub fn bool(mut self, key: &str, value: bool) -> Self {
         self.context.insert(key.into(), Context::Bool(value));
         self
     }
 
-    pub fn list<'a>(mut self, key: &str, value: Vec<Context>) -> Self {
+    pub fn list(mut self, key: &str, value: Vec<Context>) -> Self {
         self.context.insert(key.into(), Context::List(value));
         self
     }
 
-    pub fn map<'a>(mut self, key: &str, value: HashMap<String, Context>) -> Self {
+    pub fn map(mut self, key: &str, value: HashMap<String, Context>) -> Self {
         self.context.insert(key.into(), Context::Map(value));
         self
     }
@@ -96,32 +97,32 @@ impl VecBuilder {
         VecBuilder::default()
     }
 
-    pub fn str<'a>(mut self, value: &str) -> Self {
+    pub fn str(mut self, value: &str) -> Self {
         self.context.push(Context::String(value.into()));
         self
     }
 
-    pub fn int<'a>(mut self, value: i64) -> Self {
+    pub fn int(mut self, value: i64) -> Self {
         self.context.push(Context::Integer(value));
         self
     }
 
-    pub fn float<'a>(mut self, value: f64) -> Self {
+    pub fn float(mut self, value: f64) -> Self {
         self.context.push(Context::Float(value));
         self
     }
 
-    pub fn bool<'a>(mut self, value: bool) -> Self {
+    pub fn bool(mut self, value: bool) -> Self {
         self.context.push(Context::Bool(value));
         self
     }
 
-    pub fn list<'a>(mut self, value: Vec<Context>) -> Self {
+    pub fn list(mut self, value: Vec<Context>) -> Self {
         self.context.push(Context::List(value));
         self
     }
 
-    pub fn map<'a>(mut self, value: HashMap<String, Context>) -> Self {
+    pub fn map(mut self, value: HashMap<String, Context>) -> Self {
         self.context.push(Context::Map(value));
         self
     }
@@ -0,0 +1,360 @@
+//! A lossless syntax tree for rewriting templates.
+//!
+//! Unlike the tree produced by [`Template::compile`](crate::Template::compile),
+//! the syntax tree keeps comments, set-delimiter tags, the whitespace inside
+//! tags and the whitespace around standalone tags. Printing an unedited tree
+//! reproduces its source byte for byte.
+//!
+//! ```
+//! use stache::cst::{SyntaxTree, TagKind};
+//!
+//! let text = "{{! greeting }}\n  {{#user}}Hello {{ name }}!{{/user}}\n";
+//! let mut tree = SyntaxTree::parse(text).unwrap();
+//! assert_eq!(tree.to_string(), text);
+//!
+//! for tag in tree.tags_mut() {
+//!     if tag.kind == TagKind::Variable && tag.content == "name" {
+//!         tag.content = "nickname".into();
+//!     }
+//! }
+//! assert_eq!(
+//!     tree.to_string(),
+//!     "{{! greeting }}\n  {{#user}}Hello {{ nickname }}!{{/user}}\n"
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::vec::IntoIter;
+
+use crate::ast::{Span, Variant};
+use crate::error::{Error, Limit, Result};
+use crate::lexer::{Lexer, Token};
+use crate::options::MAX_DEPTH;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxTree<'t> {
+    pub nodes: Vec<SyntaxNode<'t>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxNode<'t> {
+    /// Literal text, whitespace or a newline.
+    Text(Cow<'t, str>),
+    /// Any tag that does not open a section.
+    Tag(Tag<'t>),
+    Section(SyntaxSection<'t>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxSection<'t> {
+    pub open: Tag<'t>,
+    pub children: Vec<SyntaxNode<'t>>,
+    /// The closing tag, or `None` if the section is never closed.
+    pub close: Option<Tag<'t>>,
+}
+
+/// A tag as written in the source. A tag prints as its delimiters, whitespace
+/// control markers and sigils wrapped around `leading`, `content` and
+/// `trailing`, so edits to any of the fields are reflected in the printed
+/// output.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tag<'t> {
+    pub kind: TagKind,
+    pub open_delim: Cow<'t, str>,
+    pub close_delim: Cow<'t, str>,
+    /// Whether a `~` just inside the opening delimiter trims the whitespace
+    /// before the tag, as in `{{~name}}`, under the `WHITESPACE-CONTROL`
+    /// pragma.
+    pub trim_before: bool,
+    /// Whether a `~` just inside the closing delimiter trims the whitespace
+    /// after the tag, as in `{{name~}}`.
+    pub trim_after: bool,
+    /// Whitespace between the sigil and the content.
+    pub leading: Cow<'t, str>,
+    /// The name of the tag, the text of a comment, or the new delimiters of a
+    /// set-delimiter tag.
+    pub content: Cow<'t, str>,
+    /// Whitespace between the content and the closing sigil.
+    pub trailing: Cow<'t, str>,
+    /// The location of the tag in the source it was parsed from.
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TagKind {
+    /// `{{name}}`
+    Variable,
+    /// `{{&name}}`
+    Unescaped,
+    /// `{{{name}}}`
+    Triple,
+    /// `{{#name}}`
+    Section,
+    /// `{{^name}}`
+    InvertedSection,
+    /// `{{/name}}`
+    SectionEnd,
+    /// `{{>name}}`
+    Partial,
+    /// `{{!text}}`
+    Comment,
+    /// `{{=open close=}}`
+    SetDelim,
+    /// `{{%NAME option=value}}`
+    Pragma,
+}
+
+impl TagKind {
+    /// Returns the sigils written after the opening and before the closing
+    /// delimiter.
+    pub fn sigils(self) -> (&'static str, &'static str) {
+        match self {
+            TagKind::Variable => ("", ""),
+            TagKind::Unescaped => ("&", ""),
+            TagKind::Triple => ("{", "}"),
+            TagKind::Section => ("#", ""),
+            TagKind::InvertedSection => ("^", ""),
+            TagKind::SectionEnd => ("/", ""),
+            TagKind::Partial => (">", ""),
+            TagKind::Comment => ("!", ""),
+            TagKind::SetDelim => ("=", "="),
+            TagKind::Pragma => ("%", ""),
+        }
+    }
+}
+
+impl<'t> SyntaxTree<'t> {
+    pub fn parse(text: &'t str) -> Result<Self> {
+        let tokens = Lexer::new(text).raw_tokens()?;
+        let mut builder = Builder {
+            text,
+            tokens: tokens.into_iter(),
+            open_delim: "{{",
+            close_delim: "}}",
+            whitespace_control: false,
+            depth: 0,
+        };
+        let (nodes, close) = builder.nodes(None)?;
+        if let Some(close) = close {
+            return Err(Error::parse(text, close.span));
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Returns every tag in source order, including the tags that open and
+    /// close sections.
+    pub fn tags(&self) -> Vec<&Tag<'t>> {
+        let mut tags = Vec::new();
+        Self::collect(&self.nodes, &mut tags);
+        tags
+    }
+
+    /// Returns every tag in source order for editing.
+    pub fn tags_mut(&mut self) -> Vec<&mut Tag<'t>> {
+        let mut tags = Vec::new();
+        Self::collect_mut(&mut self.nodes, &mut tags);
+        tags
+    }
+
+    fn collect<'a>(nodes: &'a [SyntaxNode<'t>], tags: &mut Vec<&'a Tag<'t>>) {
+        for node in nodes {
+            match node {
+                SyntaxNode::Text(_) => (),
+                SyntaxNode::Tag(tag) => tags.push(tag),
+                SyntaxNode::Section(section) => {
+                    tags.push(&section.open);
+                    Self::collect(&section.children, tags);
+                    tags.extend(&section.close);
+                }
+            }
+        }
+    }
+
+    fn collect_mut<'a>(nodes: &'a mut [SyntaxNode<'t>], tags: &mut Vec<&'a mut Tag<'t>>) {
+        for node in nodes {
+            match node {
+                SyntaxNode::Text(_) => (),
+                SyntaxNode::Tag(tag) => tags.push(tag),
+                SyntaxNode::Section(section) => {
+                    tags.push(&mut section.open);
+                    Self::collect_mut(&mut section.children, tags);
+                    tags.extend(&mut section.close);
+                }
+            }
+        }
+    }
+}
+
+/// Groups raw tokens into sections, keeping track of the delimiters in effect
+/// so that each tag can be split into its parts.
+struct Builder<'t> {
+    text: &'t str,
+    tokens: IntoIter<(Token<'t>, Span)>,
+    open_delim: &'t str,
+    close_delim: &'t str,
+    /// Whether `~` just inside the delimiters is a whitespace control marker,
+    /// as the `WHITESPACE-CONTROL` pragma makes it.
+    whitespace_control: bool,
+    /// How many sections enclose the current node. Sections may nest as
+    /// deeply as a compiled template's do by default.
+    depth: usize,
+}
+
+impl<'t> Builder<'t> {
+    /// Collects nodes until the tokens run out or a section is closed,
+    /// returning the closing tag in the latter case.
+    fn nodes(&mut self, section: Option<&str>) -> Result<(Vec<SyntaxNode<'t>>, Option<Tag<'t>>)> {
+        let mut nodes = Vec::new();
+        while let Some((token, span)) = self.tokens.next() {
+            let node = match token {
+                // Text is taken from the source, which includes the tags
+                // around the content of a verbatim block.
+                Token::Text(_) | Token::Whitespace(_) | Token::Newline(_) => {
+                    SyntaxNode::Text(self.text[span.start..span.end].into())
+                }
+                Token::Variable(_, true, _) => SyntaxNode::Tag(self.tag(TagKind::Variable, span)),
+                Token::Variable(..) => {
+                    let (_, inner, _) = self.inner(TagKind::Unescaped, span);
+                    let kind = match inner.starts_with('{') {
+                        true => TagKind::Triple,
+                        false => TagKind::Unescaped,
+                    };
+                    SyntaxNode::Tag(self.tag(kind, span))
+                }
+                Token::Partial(..) => SyntaxNode::Tag(self.tag(TagKind::Partial, span)),
+                Token::Comment => SyntaxNode::Tag(self.tag(TagKind::Comment, span)),
+                Token::Pragma(pragma) => {
+                    let tag = self.tag(TagKind::Pragma, span);
+                    if pragma.name == "WHITESPACE-CONTROL" {
+                        self.whitespace_control = pragma.enabled();
+                    }
+                    SyntaxNode::Tag(tag)
+                }
+                Token::Extension(..) => {
+                    unreachable!("the syntax tree is parsed without extensions")
+                }
+                Token::SetDelim(open, close) => {
+                    let tag = self.tag(TagKind::SetDelim, span);
+                    self.open_delim = open;
+                    self.close_delim = close;
+                    SyntaxNode::Tag(tag)
+                }
+                Token::SectionStart(name, variant) => {
+                    let kind = match variant {
+                        Variant::Direct => TagKind::Section,
+                        Variant::Inverse => TagKind::InvertedSection,
+                    };
+                    if self.depth == MAX_DEPTH {
+                        return Err(Error::Limit(Limit::Nesting));
+                    }
+                    let open = self.tag(kind, span);
+                    self.depth += 1;
+                    let (children, close) = self.nodes(Some(name))?;
+                    self.depth -= 1;
+                    SyntaxNode::Section(SyntaxSection {
+                        open,
+                        children,
+                        close,
+                    })
+                }
+                Token::SectionEnd(name) => {
+                    if section != Some(name) {
+                        return Err(Error::parse(self.text, span));
+                    }
+                    let close = self.tag(TagKind::SectionEnd, span);
+                    return Ok((nodes, Some(close)));
+                }
+            };
+            nodes.push(node);
+        }
+        Ok((nodes, None))
+    }
+
+    /// Returns the text of a tag between its delimiters, without the
+    /// whitespace control markers, along with whether each marker is there.
+    fn inner(&self, kind: TagKind, span: Span) -> (bool, &'t str, bool) {
+        let raw = &self.text[span.start..span.end];
+        let inner = &raw[self.open_delim.len()..raw.len() - self.close_delim.len()];
+        if !self.whitespace_control || kind == TagKind::SetDelim {
+            return (false, inner, false);
+        }
+        let (before, inner) = match inner.strip_prefix('~') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let (after, inner) = match inner.strip_suffix('~') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        (before, inner, after)
+    }
+
+    fn tag(&self, kind: TagKind, span: Span) -> Tag<'t> {
+        let (open_sigil, close_sigil) = kind.sigils();
+        let (trim_before, inner, trim_after) = self.inner(kind, span);
+        let body = &inner[open_sigil.len()..inner.len() - close_sigil.len()];
+        let content = body.trim();
+        let leading = &body[..body.len() - body.trim_start().len()];
+        let trailing = &body[leading.len() + content.len()..];
+        Tag {
+            kind,
+            open_delim: self.open_delim.into(),
+            close_delim: self.close_delim.into(),
+            trim_before,
+            trim_after,
+            leading: leading.into(),
+            content: content.into(),
+            trailing: trailing.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{node}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxNode::Text(text) => write!(f, "{text}"),
+            SyntaxNode::Tag(tag) => write!(f, "{tag}"),
+            SyntaxNode::Section(section) => {
+                write!(f, "{}", section.open)?;
+                for child in &section.children {
+                    write!(f, "{child}")?;
+                }
+                match &section.close {
+                    Some(close) => write!(f, "{close}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Tag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (open_sigil, close_sigil) = self.kind.sigils();
+        let marker = |trim: bool| if trim { "~" } else { "" };
+        write!(
+            f,
+            "{}{}{}{}{}{}{}{}{}",
+            self.open_delim,
+            marker(self.trim_before),
+            open_sigil,
+            self.leading,
+            self.content,
+            self.trailing,
+            close_sigil,
+            marker(self.trim_after),
+            self.close_delim
+        )
+    }
+}
@@ -1,6 +1,144 @@
+use std::fmt;
+
+use crate::ast::{Position, Span};
+
 #[derive(Debug, PartialEq)]
 pub enum Error {
-    Parse,
+    /// The template source is malformed at the given location.
+    Parse { span: Span, position: Position },
+    /// A variable or section name is malformed, such as `a..b` or `.a`.
+    Name {
+        name: String,
+        span: Span,
+        position: Position,
+    },
+    /// Rendering nested sections and partials more deeply than the maximum
+    /// depth allows. Holds the chain of sections and partials being rendered
+    /// at the time, with sections written as `#name`.
+    Depth(Vec<String>),
+    /// Compiling or rendering exceeded one of the configured resource limits.
+    Limit(Limit),
+    /// A filter is unknown, or failed on its value or arguments.
+    Filter { name: String, message: String },
+    /// A helper is unknown, or failed on its arguments.
+    Helper { name: String, message: String },
+    /// A pragma is unknown, or has an unknown option or value.
+    Pragma {
+        name: String,
+        message: String,
+        span: Span,
+        position: Position,
+    },
+    /// An extension failed to parse or render a tag with its sigil, or no
+    /// extension has the sigil of a tag being rendered.
+    Extension { sigil: char, message: String },
+    /// The delimiters or sigils given to the compile options cannot be used,
+    /// such as an empty delimiter or a sigil that marks two kinds of tag.
+    Syntax(String),
+    /// A name was not found while rendering a template with the `STRICT`
+    /// pragma.
+    Missing(String),
+    /// A name with `../` walked past the context passed to the render.
+    Scope(String),
+    /// Formatting the template would change its rendered output.
+    Format,
+}
+
+/// The resource limit that stopped a compile or render.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Limit {
+    Size,
+    Nesting,
+    Output,
+    Iterations,
+    Partials,
+    Deadline,
+    Cancelled,
 }
 
+impl Error {
+    pub(crate) fn parse(text: &str, span: Span) -> Self {
+        Error::Parse {
+            span,
+            position: Position::locate(text, span.start),
+        }
+    }
+
+    pub(crate) fn pragma(text: &str, name: &str, message: &str, span: Span) -> Self {
+        Error::Pragma {
+            name: name.into(),
+            message: message.into(),
+            span,
+            position: Position::locate(text, span.start),
+        }
+    }
+
+    pub(crate) fn name(text: &str, name: &str, span: Span) -> Self {
+        Error::Name {
+            name: name.into(),
+            span,
+            position: Position::locate(text, span.start),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse { position, .. } => write!(
+                f,
+                "failed to parse template at {}:{}",
+                position.line, position.column
+            ),
+            Error::Name { name, position, .. } => write!(
+                f,
+                "invalid name `{name}` at {}:{}",
+                position.line, position.column
+            ),
+            Error::Depth(chain) if chain.is_empty() => write!(f, "maximum render depth exceeded"),
+            Error::Depth(chain) => {
+                write!(
+                    f,
+                    "maximum render depth exceeded: {} > ...",
+                    chain.join(" > ")
+                )
+            }
+            Error::Limit(limit) => write!(f, "{limit}"),
+            Error::Filter { name, message } => write!(f, "filter `{name}` failed: {message}"),
+            Error::Helper { name, message } => write!(f, "helper `{name}` failed: {message}"),
+            Error::Pragma {
+                name,
+                message,
+                position,
+                ..
+            } => write!(
+                f,
+                "invalid pragma `{name}` at {}:{}: {message}",
+                position.line, position.column
+            ),
+            Error::Extension { sigil, message } => write!(f, "`{sigil}` tag failed: {message}"),
+            Error::Syntax(message) => write!(f, "{message}"),
+            Error::Missing(name) => write!(f, "`{name}` is not defined"),
+            Error::Scope(name) => write!(f, "`{name}` walks past the root context"),
+            Error::Format => write!(f, "formatting would change the rendered output"),
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Size => write!(f, "template source exceeds the maximum size"),
+            Limit::Nesting => write!(f, "template sections exceed the maximum nesting"),
+            Limit::Output => write!(f, "rendered output exceeds the maximum size"),
+            Limit::Iterations => write!(f, "render exceeds the maximum section iterations"),
+            Limit::Partials => write!(f, "render exceeds the maximum partial expansions"),
+            Limit::Deadline => write!(f, "render exceeded its deadline"),
+            Limit::Cancelled => write!(f, "render was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,83 @@
+//! Tags with sigils registered by the application, such as `{{@icon star}}`,
+//! which parse their content when the template is compiled and write their
+//! output when it is rendered.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::render::ContextResolver;
+
+/// Parses the content of a tag into the node that renders it, or returns a
+/// message explaining why the content is malformed.
+pub(crate) type ParseFn =
+    dyn Fn(&str) -> std::result::Result<Arc<dyn Any + Send + Sync>, String> + Send + Sync;
+
+/// Renders a node the parse hook of the same extension returned.
+pub(crate) type RenderFn =
+    dyn Fn(&(dyn Any + Send + Sync), &ContextResolver, &mut Output) -> Result<()> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct Extension {
+    pub parse: Arc<ParseFn>,
+    pub render: Arc<RenderFn>,
+}
+
+/// The extensions registered for a compile, by sigil.
+#[derive(Clone, Default)]
+pub(crate) struct Extensions {
+    custom: HashMap<char, Extension>,
+}
+
+impl Extensions {
+    pub fn insert(&mut self, sigil: char, extension: Extension) {
+        self.custom.insert(sigil, extension);
+    }
+
+    pub fn get(&self, sigil: char) -> Option<&Extension> {
+        self.custom.get(&sigil)
+    }
+
+    pub fn sigils(&self) -> Vec<char> {
+        self.custom.keys().copied().collect()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sigils = self.sigils();
+        sigils.sort();
+        f.debug_set().entries(sigils).finish()
+    }
+}
+
+/// Writes to the output of a render.
+pub(crate) trait Write {
+    fn write(&mut self, text: &str) -> Result<()>;
+
+    fn write_escaped(&mut self, text: &str) -> Result<()>;
+}
+
+/// The output an extension writes the rendering of its tag to, which counts
+/// towards the same limits as the rest of the render.
+pub struct Output<'o> {
+    out: &'o mut (dyn Write + 'o),
+}
+
+impl<'o> Output<'o> {
+    pub(crate) fn new(out: &'o mut (dyn Write + 'o)) -> Self {
+        Self { out }
+    }
+
+    /// Writes text as it is.
+    pub fn write(&mut self, text: &str) -> Result<()> {
+        self.out.write(text)
+    }
+
+    /// Writes text with HTML special characters escaped, as variable tags do.
+    pub fn write_escaped(&mut self, text: &str) -> Result<()> {
+        self.out.write_escaped(text)
+    }
+}
@@ -0,0 +1,264 @@
+//! Filters that variable tags pass their value through, as in
+//! `{{title | truncate 20}}`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::context::Context;
+
+/// A filter takes the value so far, which is `None` if the name was not
+/// found, and the arguments written after the filter's name. It returns the
+/// new value or a message explaining why the value or arguments do not fit.
+pub(crate) type FilterFn =
+    dyn Fn(Option<&Context>, &[&str]) -> Result<Option<Context>, String> + Send + Sync;
+
+/// The filters registered for a render, which take precedence over the
+/// built-in filters of the same name.
+#[derive(Clone, Default)]
+pub(crate) struct Filters {
+    custom: HashMap<String, Arc<FilterFn>>,
+}
+
+impl Filters {
+    pub fn insert(&mut self, name: &str, filter: Arc<FilterFn>) {
+        self.custom.insert(name.into(), filter);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FilterFn> {
+        match self.custom.get(name) {
+            Some(filter) => Some(filter.as_ref()),
+            None => builtin(name),
+        }
+    }
+}
+
+impl fmt::Debug for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.custom.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+fn builtin(name: &str) -> Option<&'static FilterFn> {
+    let filter: &'static FilterFn = match name {
+        "upper" => &upper,
+        "lower" => &lower,
+        "trim" => &trim,
+        "truncate" => &truncate,
+        "default" => &default,
+        "date" => &date,
+        "json" => &json,
+        "urlencode" => &urlencode,
+        "pluralize" => &pluralize,
+        _ => return None,
+    };
+    Some(filter)
+}
+
+/// Returns the text of a scalar value, or `None` if the value is missing.
+fn text(value: Option<&Context>) -> Result<Option<String>, String> {
+    match value {
+        None => Ok(None),
+        Some(Context::Map(_) | Context::List(_)) => Err("expected a scalar value".into()),
+        Some(value) => Ok(Some(value.to_text())),
+    }
+}
+
+fn map_text(
+    value: Option<&Context>,
+    f: impl Fn(&str) -> String,
+) -> Result<Option<Context>, String> {
+    Ok(text(value)?.map(|text| Context::String(f(&text))))
+}
+
+fn upper(value: Option<&Context>, _: &[&str]) -> Result<Option<Context>, String> {
+    map_text(value, str::to_uppercase)
+}
+
+fn lower(value: Option<&Context>, _: &[&str]) -> Result<Option<Context>, String> {
+    map_text(value, str::to_lowercase)
+}
+
+fn trim(value: Option<&Context>, _: &[&str]) -> Result<Option<Context>, String> {
+    map_text(value, |text| text.trim().into())
+}
+
+/// `truncate n [suffix]` keeps the first `n` characters, followed by the
+/// suffix, `...` by default, if any were cut off.
+fn truncate(value: Option<&Context>, args: &[&str]) -> Result<Option<Context>, String> {
+    let Some(len) = args.first().and_then(|arg| arg.parse::<usize>().ok()) else {
+        return Err("expected a number of characters".into());
+    };
+    let suffix = args.get(1).copied().unwrap_or("...");
+    map_text(value, |text| match text.char_indices().nth(len) {
+        Some((end, _)) => format!("{}{suffix}", &text[..end]),
+        None => text.into(),
+    })
+}
+
+/// `default text` replaces a missing, falsy or empty value.
+fn default(value: Option<&Context>, args: &[&str]) -> Result<Option<Context>, String> {
+    let [fallback] = args else {
+        return Err("expected a default value".into());
+    };
+    match value {
+        Some(Context::String(text)) if text.is_empty() => {
+            Ok(Some(Context::String(fallback.to_string())))
+        }
+        Some(value) if value.is_truthy() => Ok(Some(value.clone())),
+        _ => Ok(Some(Context::String(fallback.to_string()))),
+    }
+}
+
+/// `date [format]` formats a number of seconds since the Unix epoch, in UTC.
+/// The format supports `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%%`, and is
+/// `%Y-%m-%d` by default.
+fn date(value: Option<&Context>, args: &[&str]) -> Result<Option<Context>, String> {
+    let seconds = match value {
+        None => return Ok(None),
+        Some(Context::Integer(seconds)) => *seconds,
+        Some(Context::Float(seconds)) => seconds.floor() as i64,
+        Some(_) => return Err("expected a number of seconds".into()),
+    };
+    let format = args.first().copied().unwrap_or("%Y-%m-%d");
+    let (year, month, day) = civil(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let _ = match chars.next() {
+            Some('Y') => write!(out, "{year:04}"),
+            Some('m') => write!(out, "{month:02}"),
+            Some('d') => write!(out, "{day:02}"),
+            Some('H') => write!(out, "{:02}", time / 3600),
+            Some('M') => write!(out, "{:02}", time / 60 % 60),
+            Some('S') => write!(out, "{:02}", time % 60),
+            Some('%') => write!(out, "%"),
+            _ => return Err(format!("unsupported format `{format}`")),
+        };
+    }
+    Ok(Some(Context::String(out)))
+}
+
+/// Converts days since the Unix epoch into a year, month and day of the
+/// proleptic Gregorian calendar.
+fn civil(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `json` writes the value as JSON, with the keys of maps sorted. A missing
+/// value is written as `null`.
+fn json(value: Option<&Context>, _: &[&str]) -> Result<Option<Context>, String> {
+    let mut out = String::new();
+    write_json(&mut out, value.unwrap_or(&Context::Null));
+    Ok(Some(Context::String(out)))
+}
+
+fn write_json(out: &mut String, value: &Context) {
+    match value {
+        Context::String(text) => write_json_string(out, text),
+        Context::Integer(number) => out.push_str(&number.to_string()),
+        Context::Float(number) if number.is_finite() => out.push_str(&number.to_string()),
+        Context::Float(_) | Context::Null => out.push_str("null"),
+        Context::Bool(value) => out.push_str(&value.to_string()),
+        Context::List(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(out, item);
+            }
+            out.push(']');
+        }
+        Context::Map(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json_string(out, key);
+                out.push(':');
+                write_json(out, value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// `urlencode` percent-encodes every byte but the unreserved characters of
+/// RFC 3986.
+fn urlencode(value: Option<&Context>, _: &[&str]) -> Result<Option<Context>, String> {
+    map_text(value, |text| {
+        let mut out = String::with_capacity(text.len());
+        for byte in text.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    out.push(byte as char)
+                }
+                byte => {
+                    let _ = write!(out, "%{byte:02X}");
+                }
+            }
+        }
+        out
+    })
+}
+
+/// `pluralize [singular] [plural]` picks a suffix, or a word, by whether a
+/// count, or the length of a list, is one. Without arguments it gives `s`
+/// for anything but one.
+fn pluralize(value: Option<&Context>, args: &[&str]) -> Result<Option<Context>, String> {
+    let one = match value {
+        Some(Context::Integer(count)) => *count == 1,
+        Some(Context::Float(count)) => *count == 1.0,
+        Some(Context::List(items)) => items.len() == 1,
+        None | Some(Context::Null) => false,
+        Some(_) => return Err("expected a number or a list".into()),
+    };
+    let (singular, plural) = match args {
+        [] => ("", "s"),
+        [plural] => ("", *plural),
+        [singular, plural] => (*singular, *plural),
+        _ => return Err("expected at most two words".into()),
+    };
+    let word = if one { singular } else { plural };
+    Ok(Some(Context::String(word.into())))
+}
@@ -0,0 +1,213 @@
+use crate::ast::Span;
+use crate::cst::{SyntaxNode, SyntaxTree, Tag, TagKind};
+use crate::error::{Error, Result};
+use crate::lexer::{Lexer, Token};
+
+/// How unescaped variables are written by the formatter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnescapedStyle {
+    /// `{{{name}}}`
+    Triple,
+    /// `{{&name}}`
+    Ampersand,
+}
+
+/// Settings that control how templates are formatted.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub(crate) unescaped: UnescapedStyle,
+    pub(crate) indent: String,
+    pub(crate) padding: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            unescaped: UnescapedStyle::Triple,
+            indent: String::from("  "),
+            padding: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        FormatOptions::default()
+    }
+
+    /// Sets how unescaped variables are written.
+    pub fn unescaped(mut self, style: UnescapedStyle) -> Self {
+        self.unescaped = style;
+        self
+    }
+
+    /// Sets the whitespace standalone tags are indented by for each enclosing
+    /// section.
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Sets whether tag names are padded with a space, as in `{{ name }}`.
+    pub fn padding(mut self, padding: bool) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+/// Formats a template with the default options.
+pub fn format(text: &str) -> Result<String> {
+    format_with_options(text, FormatOptions::default())
+}
+
+/// Formats a template. Tags are written with consistent spacing and a single
+/// style for unescaped variables, and lines holding only a section tag,
+/// comment or set-delimiter tag are indented to match their nesting. Lines
+/// holding a standalone partial keep their indentation, which the partial
+/// inherits.
+///
+/// The tokens of the formatted template are compared with those of the
+/// original, and formatting fails with `Error::Format` if they differ, as the
+/// rendered output could then differ as well.
+pub fn format_with_options(text: &str, options: FormatOptions) -> Result<String> {
+    let mut tree = SyntaxTree::parse(text)?;
+    for tag in tree.tags_mut() {
+        normalize(tag, &options);
+    }
+
+    let mut items = Vec::new();
+    flatten(&tree.nodes, 0, &mut items);
+    let mut out = String::new();
+    for line in items.split_inclusive(|item| matches!(item, Item::Text(text) if is_newline(text))) {
+        write_line(line, &options, &mut out);
+    }
+
+    if !equivalent(text, &out)? {
+        return Err(Error::Format);
+    }
+    Ok(out)
+}
+
+fn normalize(tag: &mut Tag, options: &FormatOptions) {
+    tag.kind = match (tag.kind, options.unescaped) {
+        (TagKind::Triple, UnescapedStyle::Ampersand) => TagKind::Unescaped,
+        (TagKind::Unescaped, UnescapedStyle::Triple) => TagKind::Triple,
+        (kind, _) => kind,
+    };
+    match tag.kind {
+        TagKind::Comment => (),
+        TagKind::SetDelim => {
+            let delims: Vec<&str> = tag.content.split_whitespace().collect();
+            tag.content = delims.join(" ").into();
+            tag.leading = "".into();
+            tag.trailing = "".into();
+        }
+        _ => {
+            let padding = match options.padding {
+                true => " ",
+                false => "",
+            };
+            // A variable whose name starts with a sigil is only a variable
+            // because of the space in front of it.
+            let sigil = tag.kind == TagKind::Variable
+                && tag
+                    .content
+                    .starts_with(['#', '^', '/', '>', '&', '!', '%', '=', '{']);
+            tag.leading = match sigil {
+                true => " ".into(),
+                false => padding.into(),
+            };
+            tag.trailing = padding.into();
+        }
+    }
+}
+
+enum Item<'a, 't> {
+    Text(&'a str),
+    Tag(&'a Tag<'t>, usize),
+}
+
+fn flatten<'a, 't>(nodes: &'a [SyntaxNode<'t>], depth: usize, items: &mut Vec<Item<'a, 't>>) {
+    for node in nodes {
+        match node {
+            SyntaxNode::Text(text) => items.push(Item::Text(text)),
+            SyntaxNode::Tag(tag) => items.push(Item::Tag(tag, depth)),
+            SyntaxNode::Section(section) => {
+                items.push(Item::Tag(&section.open, depth));
+                flatten(&section.children, depth + 1, items);
+                if let Some(close) = &section.close {
+                    items.push(Item::Tag(close, depth));
+                }
+            }
+        }
+    }
+}
+
+/// Writes a line, re-indenting it if it holds a standalone tag whose
+/// surrounding whitespace is dropped when rendering.
+fn write_line(line: &[Item], options: &FormatOptions, out: &mut String) {
+    let standalone = line.iter().all(|item| match item {
+        Item::Text(text) => is_whitespace(text) || is_newline(text),
+        Item::Tag(tag, _) => !matches!(
+            tag.kind,
+            TagKind::Variable | TagKind::Unescaped | TagKind::Triple
+        ),
+    });
+    let tags: Vec<(&Tag, usize)> = line
+        .iter()
+        .filter_map(|item| match item {
+            Item::Tag(tag, depth) => Some((*tag, *depth)),
+            Item::Text(_) => None,
+        })
+        .collect();
+    match (standalone, tags.as_slice()) {
+        (true, [(tag, depth)]) if tag.kind != TagKind::Partial => {
+            out.push_str(&options.indent.repeat(*depth));
+            out.push_str(&tag.to_string());
+            if let Some(Item::Text(text)) = line.last() {
+                if is_newline(text) {
+                    out.push_str(text);
+                }
+            }
+        }
+        _ => {
+            for item in line {
+                match item {
+                    Item::Text(text) => out.push_str(text),
+                    Item::Tag(tag, _) => out.push_str(&tag.to_string()),
+                }
+            }
+        }
+    }
+}
+
+fn is_newline(text: &str) -> bool {
+    text == "\n" || text == "\r\n"
+}
+
+fn is_whitespace(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c == ' ' || c == '\t')
+}
+
+/// Checks that two templates produce the same tokens once whitespace around
+/// standalone tags is removed, ignoring where the tokens are in the source.
+fn equivalent(before: &str, after: &str) -> Result<bool> {
+    let tokens = |text| -> Result<Vec<Token>> {
+        Lexer::new(text)
+            .tokens()
+            .map(|token| {
+                token.map(|(mut token, _)| {
+                    // Pragmas carry their location, which formatting moves.
+                    if let Token::Pragma(pragma) = &mut token {
+                        pragma.span = Span::default();
+                    }
+                    token
+                })
+            })
+            .collect()
+    };
+    let Ok(after) = tokens(after) else {
+        return Ok(false);
+    };
+    Ok(tokens(before)? == after)
+}
@@ -0,0 +1,222 @@
+//! Helpers that sections with arguments call, as in
+//! `{{#if_eq status "paid"}}...{{/if_eq}}`.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::context::Context;
+use crate::error::{Error, Result};
+
+/// A helper decides whether, how often and with which context the body of its
+/// section renders.
+pub(crate) type HelperFn = dyn Fn(&mut Block) -> Result<()> + Send + Sync;
+
+/// The helpers registered for a render, which take precedence over the
+/// built-in helpers of the same name.
+#[derive(Clone, Default)]
+pub(crate) struct Helpers {
+    custom: HashMap<String, Arc<HelperFn>>,
+}
+
+impl Helpers {
+    pub fn insert(&mut self, name: &str, helper: Arc<HelperFn>) {
+        self.custom.insert(name.into(), helper);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&HelperFn> {
+        match self.custom.get(name) {
+            Some(helper) => Some(helper.as_ref()),
+            None => builtin(name),
+        }
+    }
+}
+
+impl fmt::Debug for Helpers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.custom.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+/// Renders the body of a helper section and writes to the output.
+pub(crate) trait Body {
+    /// Renders the body, with a context pushed if one is given.
+    fn render(&mut self, context: Option<&Context>) -> Result<()>;
+
+    fn write(&mut self, text: &str) -> Result<()>;
+
+    fn is_truthy(&self, value: &Context) -> bool;
+}
+
+/// An argument as passed to a helper: its key if it is named, and its value,
+/// which is `None` if it is a name that was not found.
+pub(crate) type Resolved<'c> = (Option<&'c str>, Option<Cow<'c, Context>>);
+
+/// A call of a helper by a section: the arguments written in the section tag,
+/// with names already looked up, and the body of the section.
+pub struct Block<'b> {
+    name: &'b str,
+    args: &'b [Resolved<'b>],
+    body: &'b mut (dyn Body + 'b),
+}
+
+impl<'b> Block<'b> {
+    pub(crate) fn new(
+        name: &'b str,
+        args: &'b [Resolved<'b>],
+        body: &'b mut (dyn Body + 'b),
+    ) -> Self {
+        Self { name, args, body }
+    }
+
+    /// Returns the name the helper was called by.
+    pub fn name(&self) -> &'b str {
+        self.name
+    }
+
+    /// Returns the number of positional arguments.
+    pub fn arg_count(&self) -> usize {
+        self.positional().count()
+    }
+
+    /// Returns a positional argument, counting from zero. `None` if there is
+    /// no such argument, or if it is a name that was not found.
+    pub fn arg(&self, index: usize) -> Option<&'b Context> {
+        self.positional().nth(index)?.as_deref()
+    }
+
+    /// Returns a named argument, written as `key=value`.
+    pub fn named(&self, key: &str) -> Option<&'b Context> {
+        let args: &'b [Resolved<'b>] = self.args;
+        args.iter()
+            .find(|(name, _)| *name == Some(key))
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Renders the body of the section in the current context.
+    pub fn render(&mut self) -> Result<()> {
+        self.body.render(None)
+    }
+
+    /// Renders the body of the section with a context pushed, so that names
+    /// are looked up in it before the enclosing contexts.
+    pub fn render_with(&mut self, context: &Context) -> Result<()> {
+        self.body.render(Some(context))
+    }
+
+    /// Writes text to the output as it is, without escaping it.
+    pub fn write(&mut self, text: &str) -> Result<()> {
+        self.body.write(text)
+    }
+
+    /// Whether a value is truthy under the rules of the render, as sections
+    /// decide whether to render their body.
+    pub fn is_truthy(&self, value: &Context) -> bool {
+        self.body.is_truthy(value)
+    }
+
+    /// Creates the error a helper returns when its arguments do not fit.
+    pub fn error(&self, message: impl Into<String>) -> Error {
+        Error::Helper {
+            name: self.name.into(),
+            message: message.into(),
+        }
+    }
+
+    fn positional(&self) -> impl Iterator<Item = &'b Option<Cow<'b, Context>>> {
+        let args: &'b [Resolved<'b>] = self.args;
+        args.iter()
+            .filter(|(key, _)| key.is_none())
+            .map(|(_, value)| value)
+    }
+}
+
+fn builtin(name: &str) -> Option<&'static HelperFn> {
+    let helper: &'static HelperFn = match name {
+        "if_eq" => &if_eq,
+        "each_sorted" => &each_sorted,
+        "with" => &with,
+        _ => return None,
+    };
+    Some(helper)
+}
+
+/// `if_eq a b` renders its body if both values are scalars with the same
+/// text.
+fn if_eq(block: &mut Block) -> Result<()> {
+    if block.arg_count() != 2 {
+        return Err(block.error("expected two values"));
+    }
+    let text = |value: Option<&Context>| match value {
+        None | Some(Context::Map(_) | Context::List(_)) => None,
+        Some(value) => Some(value.to_text()),
+    };
+    match (text(block.arg(0)), text(block.arg(1))) {
+        (Some(a), Some(b)) if a == b => block.render(),
+        _ => Ok(()),
+    }
+}
+
+/// `each_sorted list [key=name]` renders its body for each item of a list in
+/// ascending order, comparing the items or, given a key, the values the key
+/// names within them.
+fn each_sorted(block: &mut Block) -> Result<()> {
+    let items = match block.arg(0) {
+        Some(Context::List(items)) => items,
+        None | Some(Context::Null) => return Ok(()),
+        Some(_) => return Err(block.error("expected a list")),
+    };
+    let key = match block.named("key") {
+        None => None,
+        Some(Context::String(key)) => Some(key.as_str()),
+        Some(_) => return Err(block.error("expected `key` to be a name")),
+    };
+    let sort_value = |item| match key {
+        None => Some(item),
+        Some(key) => key.split('.').try_fold(item, Context::get),
+    };
+    let mut sorted: Vec<&Context> = items.iter().collect();
+    sorted.sort_by(|a, b| compare(sort_value(a), sort_value(b)));
+    for item in sorted {
+        block.render_with(item)?;
+    }
+    Ok(())
+}
+
+/// Orders missing values first, then numbers by value, then other scalars by
+/// their text, then maps and lists, which compare equal to each other. This
+/// is a total order, as sorting requires.
+fn compare(a: Option<&Context>, b: Option<&Context>) -> Ordering {
+    let rank = |value: Option<&Context>| match value {
+        None | Some(Context::Null) => 0,
+        Some(Context::Integer(_) | Context::Float(_)) => 1,
+        Some(Context::String(_) | Context::Bool(_)) => 2,
+        Some(Context::Map(_) | Context::List(_)) => 3,
+    };
+    let number = |value: Option<&Context>| match value {
+        Some(Context::Integer(number)) => *number as f64,
+        Some(Context::Float(number)) => *number,
+        _ => 0.0,
+    };
+    match (rank(a), rank(b)) {
+        (1, 1) => number(a).total_cmp(&number(b)),
+        (2, 2) => a.map(Context::to_text).cmp(&b.map(Context::to_text)),
+        (a, b) => a.cmp(&b),
+    }
+}
+
+/// `with value` renders its body once with the value pushed, if the value is
+/// truthy. Unlike a section, it does not iterate over lists.
+fn with(block: &mut Block) -> Result<()> {
+    if block.arg_count() != 1 {
+        return Err(block.error("expected one value"));
+    }
+    match block.arg(0) {
+        Some(value) if block.is_truthy(value) => block.render_with(value),
+        _ => Ok(()),
+    }
+}
@@ -1,24 +1,78 @@
-use crate::ast::Variant;
+//! The scanner that splits a template into tokens. The parser and the
+//! syntax tree are both built from these tokens; the scanner is exposed so
+//! that tools and benchmarks can read a template without building either.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use crate::ast::{Filter, Pragma, Span, Variant};
 use crate::error::{Error, Result};
+use crate::options::Sigils;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token<'t> {
     Text(&'t str),
     Newline(&'t str),
     Whitespace(&'t str),
-    Variable(&'t str, bool),
+    Variable(&'t str, bool, Vec<Filter<'t>>),
     SectionStart(&'t str, Variant),
     SectionEnd(&'t str),
     Partial(&'t str, String),
     SetDelim(&'t str, &'t str),
     Comment,
+    Pragma(Pragma<'t>),
+    Extension(char, &'t str),
 }
 
 pub struct Lexer<'t> {
     text: &'t str,
     pos: usize,
-    open_delim: &'t str,
-    close_delim: &'t str,
+    /// The current delimiters, borrowed from the template once a
+    /// set-delimiter tag has changed them.
+    open_delim: Cow<'t, str>,
+    close_delim: Cow<'t, str>,
+    patterns: Patterns,
+    sigils: Sigils,
+    /// Position of the next opening delimiter at or after `pos`, or the end of
+    /// the text if there is none. `None` when it needs to be searched for.
+    next_open: Option<usize>,
+    /// Whether variable tags may pass their value through filters.
+    filters: bool,
+    /// Whether `~` just inside the delimiters of a tag trims the whitespace
+    /// on that side of it.
+    whitespace_control: bool,
+    /// Whether only whitespace, comments and pragmas have been scanned so
+    /// far, which pragmas must follow.
+    preamble: bool,
+    /// The sigils registered by extensions.
+    extensions: Vec<char>,
+}
+
+/// Tag boundaries derived from the current delimiters, built once whenever the
+/// delimiters change rather than on every scan.
+struct Patterns {
+    set_delim_open: String,
+    set_delim_close: String,
+    triple_open: String,
+    triple_close: String,
+}
+
+impl Patterns {
+    fn new(open_delim: &str, close_delim: &str) -> Self {
+        Self {
+            set_delim_open: Self::concat(open_delim, "="),
+            set_delim_close: Self::concat("=", close_delim),
+            triple_open: Self::concat(open_delim, "{"),
+            triple_close: Self::concat("}", close_delim),
+        }
+    }
+
+    fn concat(a: &str, b: &str) -> String {
+        let mut out = String::with_capacity(a.len() + b.len());
+        out.push_str(a);
+        out.push_str(b);
+        out
+    }
 }
 
 impl<'t> Lexer<'t> {
@@ -26,91 +80,121 @@ impl<'t> Lexer<'t> {
         Self {
             text,
             pos: 0,
-            open_delim: "{{",
-            close_delim: "}}",
+            open_delim: Cow::Borrowed("{{"),
+            close_delim: Cow::Borrowed("}}"),
+            patterns: Patterns::new("{{", "}}"),
+            sigils: Sigils::default(),
+            next_open: None,
+            filters: false,
+            whitespace_control: false,
+            preamble: true,
+            extensions: Vec::new(),
         }
     }
 
-    pub fn tokens(&mut self) -> Result<Vec<Token<'t>>> {
-        let mut tokens = Vec::new();
-        while let Some(token) = self.next()? {
-            tokens.push(token);
-        }
-        Self::strip_standalone_whitespace(&mut tokens);
-        Ok(tokens)
+    /// Sets the delimiters the template starts with, which set-delimiter
+    /// tags can change.
+    pub fn delimiters(mut self, open_delim: &str, close_delim: &str) -> Self {
+        let open_delim = Cow::Owned(open_delim.to_string());
+        let close_delim = Cow::Owned(close_delim.to_string());
+        self.set_delimiters(open_delim, close_delim);
+        self
     }
 
-    fn strip_standalone_whitespace<'a>(tokens: &mut Vec<Token<'t>>) {
-        let mut ix = 0;
-        while let Some(line) = Self::line(&tokens[ix..]) {
-            let line_len = line.len();
-            let contains_text_or_var = line.iter().any(Self::is_text_or_var);
-            let special_tag_count = line.iter().filter(Self::is_special_tag).count();
-            if contains_text_or_var || special_tag_count != 1 {
-                ix += line_len;
-                continue;
-            }
+    /// Sets the characters that follow the opening delimiter to mark the
+    /// kind of a tag.
+    pub fn sigils(mut self, sigils: Sigils) -> Self {
+        self.sigils = sigils;
+        self
+    }
 
-            let tag_pos = line.iter().position(|x| Self::is_special_tag(&x)).unwrap();
-            if matches!(line[tag_pos], Token::Partial(..)) {
-                let mut indent = String::new();
-                for token in &line[..tag_pos] {
-                    let Token::Whitespace(ws) = token else {
-                        unreachable!();
-                    };
-                    indent.push_str(ws);
-                }
-                let Token::Partial(_, partial_indent) = &mut tokens[ix + tag_pos] else {
-                    unreachable!();
-                };
-                *partial_indent = indent;
-            }
+    /// Sets the sigils that mark tags parsed by extensions.
+    pub fn extensions(mut self, sigils: Vec<char>) -> Self {
+        self.extensions = sigils;
+        self
+    }
 
-            let newline_pos = ix + line_len - tag_pos;
-            tokens.drain(ix..ix + tag_pos);
-            tokens.drain(ix + 1..newline_pos);
-            ix += 1;
-        }
+    /// Sets whether `|` in variable tags separates the name from filters.
+    pub fn filters(mut self, enabled: bool) -> Self {
+        self.filters = enabled;
+        self
     }
 
-    fn is_text_or_var(token: &Token) -> bool {
-        use Token::*;
-        matches!(token, Text(_) | Variable(..))
+    /// Sets whether `~` just inside the delimiters of a tag, as in
+    /// `{{~name~}}`, trims the whitespace and newlines on that side of it.
+    pub fn whitespace_control(mut self, enabled: bool) -> Self {
+        self.whitespace_control = enabled;
+        self
     }
 
-    fn is_special_tag(token: &&Token) -> bool {
-        use Token::*;
-        matches!(
-            token,
-            SectionStart(..) | SectionEnd(_) | Partial(..) | SetDelim(..) | Comment
-        )
+    /// Returns the tokens of the template, each paired with the byte range of
+    /// the source it was read from. Whitespace around standalone tags is
+    /// removed one line at a time as the template is scanned.
+    pub fn tokens(self) -> Tokens<'t> {
+        Tokens {
+            lexer: self,
+            line: VecDeque::new(),
+            held: VecDeque::new(),
+            trim_next: false,
+            done: false,
+        }
     }
 
-    fn line<'a>(tokens: &'a [Token<'t>]) -> Option<&'a [Token<'t>]> {
-        match tokens
-            .iter()
-            .position(|token| matches!(token, Token::Newline(_)))
-        {
-            Some(pos) => Some(&tokens[..pos + 1]),
-            None => match tokens.len() > 0 {
-                true => Some(&tokens),
-                false => None,
-            },
+    /// Scans the whole template without removing any whitespace, so that the
+    /// spans of the tokens cover the source exactly.
+    pub fn raw_tokens(&mut self) -> Result<Vec<(Token<'t>, Span)>> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
         }
+        Ok(tokens)
+    }
+
+    /// Scans the next token without removing any whitespace, pairing it with
+    /// the byte range of the source it was read from.
+    pub fn next_token(&mut self) -> Result<Option<(Token<'t>, Span)>> {
+        let start = self.pos;
+        Ok(self
+            .next()?
+            .map(|token| (token, Span::new(start, self.pos))))
     }
 
     fn next(&mut self) -> Result<Option<Token<'t>>> {
+        let token = self.scan()?;
+        match &token {
+            Some(Token::Pragma(pragma)) => self.apply_pragma(pragma),
+            Some(Token::Whitespace(_) | Token::Newline(_) | Token::Comment) | None => (),
+            Some(_) => self.preamble = false,
+        }
+        Ok(token)
+    }
+
+    /// Switches the settings of the lexer that a pragma changes for the rest
+    /// of the template.
+    fn apply_pragma(&mut self, pragma: &Pragma) {
+        match pragma.name {
+            "FILTERS" => self.filters = pragma.enabled(),
+            "WHITESPACE-CONTROL" => self.whitespace_control = pragma.enabled(),
+            _ => (),
+        }
+    }
+
+    fn scan(&mut self) -> Result<Option<Token<'t>>> {
         if self.pos == self.text.len() {
             return Ok(None);
         }
 
-        while let Some((token, len)) = self.scan_set_delim()? {
+        if let Some((token, len)) = self.scan_raw_block()? {
+            self.pos += len;
+            return Ok(Some(token));
+        }
+
+        if let Some((token, len)) = self.scan_set_delim()? {
             self.pos += len;
-            let Token::SetDelim(open, close) = &token else {
-                return Err(Error::Parse);
+            let Token::SetDelim(open, close) = token else {
+                unreachable!();
             };
-            self.open_delim = open;
-            self.close_delim = close;
+            self.set_delimiters(Cow::Borrowed(open), Cow::Borrowed(close));
             return Ok(Some(token));
         }
 
@@ -139,67 +223,253 @@ impl<'t> Lexer<'t> {
         Ok(Some(token))
     }
 
+    fn set_delimiters(&mut self, open_delim: Cow<'t, str>, close_delim: Cow<'t, str>) {
+        self.patterns = Patterns::new(&open_delim, &close_delim);
+        self.open_delim = open_delim;
+        self.close_delim = close_delim;
+        self.next_open = None;
+    }
+
     fn remainder(&self) -> &'t str {
         &self.text[self.pos..]
     }
 
+    /// Reports malformed source starting at the current position.
+    fn error(&self, len: usize) -> Error {
+        Error::parse(self.text, Span::new(self.pos, self.pos + len))
+    }
+
+    /// Scans a verbatim block, `{{{{name}}}}...{{{{/name}}}}`, whose content
+    /// is text without any tags. Verbatim blocks are written with braces
+    /// whatever the current delimiters are, and do not change them.
+    fn scan_raw_block(&self) -> Result<Option<(Token<'t>, usize)>> {
+        let Some(rest) = self.remainder().strip_prefix("{{{{") else {
+            return Ok(None);
+        };
+        let Some(name_len) = rest.find("}}}}") else {
+            return Err(self.error(self.remainder().len()));
+        };
+        let name = rest[..name_len].trim();
+        let open_len = name_len + 8;
+        if name.is_empty() || name.starts_with('/') {
+            return Err(self.error(open_len));
+        }
+        let body = &rest[name_len + 4..];
+        let end = body
+            .match_indices("{{{{/")
+            .map(|(pos, _)| pos)
+            .find(|&pos| {
+                let close = &body[pos + 5..];
+                close
+                    .strip_prefix(name)
+                    .is_some_and(|close| close.starts_with("}}}}"))
+            });
+        let Some(end) = end else {
+            return Err(self.error(self.remainder().len()));
+        };
+        let len = open_len + end + name.len() + 9;
+        Ok(Some((Token::Text(&body[..end]), len)))
+    }
+
     fn scan_set_delim(&self) -> Result<Option<(Token<'t>, usize)>> {
-        let Some(remainder) = self.remainder().strip_prefix(&format!("{}=", &self.open_delim)) else {
+        let patterns = &self.patterns;
+        let Some(remainder) = self.remainder().strip_prefix(&patterns.set_delim_open) else {
             return Ok(None);
         };
-        let Some(content_len) = remainder.find(&format!("={}", &self.close_delim)) else {
-            return Err(Error::Parse);
+        let Some(content_len) = remainder.find(&patterns.set_delim_close) else {
+            return Err(self.error(self.remainder().len()));
         };
-        let mut new_delims = remainder[..content_len].trim().split_whitespace();
+        let len = content_len + self.open_delim.len() + self.close_delim.len() + 2;
+        let mut new_delims = remainder[..content_len].split_whitespace();
         let (Some(open_delim), Some(close_delim)) = (new_delims.next(), new_delims.next()) else {
-            return Err(Error::Parse);
+            return Err(self.error(len));
         };
-        Ok(Some((
-            Token::SetDelim(open_delim, close_delim),
-            content_len + self.open_delim.len() + self.close_delim.len() + 2,
-        )))
+        Ok(Some((Token::SetDelim(open_delim, close_delim), len)))
     }
 
     fn scan_triple_unescape(&self) -> Result<Option<(Token<'t>, usize)>> {
-        let Some(remainder) = self.remainder().strip_prefix(&format!("{}{{", &self.open_delim)) else {
+        if self.whitespace_control {
+            return self.scan_marked_triple();
+        }
+        let patterns = &self.patterns;
+        let Some(remainder) = self.remainder().strip_prefix(&patterns.triple_open) else {
             return Ok(None);
         };
-        let Some(content_len) = remainder.find(&format!("}}{}", &self.close_delim)) else {
-            return Err(Error::Parse);
+        let Some(content_len) = remainder.find(&patterns.triple_close) else {
+            return Err(self.error(self.remainder().len()));
         };
-        Ok(Some((
-            Token::Variable(remainder[..content_len].trim(), false),
-            content_len + self.open_delim.len() + self.close_delim.len() + 2,
-        )))
+        let len = content_len + self.open_delim.len() + self.close_delim.len() + 2;
+        let token = self.variable(&remainder[..content_len], false, len)?;
+        Ok(Some((token, len)))
+    }
+
+    /// Scans a triple mustache that may have whitespace control markers
+    /// outside its braces, as in `{{~{name}~}}`.
+    fn scan_marked_triple(&self) -> Result<Option<(Token<'t>, usize)>> {
+        let Some(inner) = self.remainder().strip_prefix(&*self.open_delim) else {
+            return Ok(None);
+        };
+        let Some(body) = inner.strip_prefix('{').or_else(|| inner.strip_prefix("~{")) else {
+            return Ok(None);
+        };
+        let close = body.find(&self.patterns.triple_close);
+        let marked_close = body
+            .match_indices("}~")
+            .map(|(pos, _)| pos)
+            .find(|&pos| body[pos + 2..].starts_with(&*self.close_delim));
+        let (content_len, close_len) = match (close, marked_close) {
+            (Some(close), Some(marked)) if marked < close => (marked, self.close_delim.len() + 2),
+            (Some(close), _) => (close, self.close_delim.len() + 1),
+            (None, Some(marked)) => (marked, self.close_delim.len() + 2),
+            (None, None) => return Err(self.error(self.remainder().len())),
+        };
+        let len = self.remainder().len() - body.len() + content_len + close_len;
+        let token = self.variable(&body[..content_len], false, len)?;
+        Ok(Some((token, len)))
     }
 
     fn scan_tag(&self) -> Result<Option<(Token<'t>, usize)>> {
-        let Some(remainder) = self.remainder().strip_prefix(&self.open_delim) else {
+        let Some(remainder) = self.remainder().strip_prefix(&*self.open_delim) else {
             return Ok(None);
         };
-        let Some(content_len) = remainder.find(&self.close_delim) else {
-            return Err(Error::Parse);
+        let Some(content_len) = remainder.find(&*self.close_delim) else {
+            return Err(self.error(self.remainder().len()));
+        };
+        let len = content_len + self.open_delim.len() + self.close_delim.len();
+        let content = self.strip_markers(&remainder[..content_len]);
+        let Some(sigil) = content.chars().next() else {
+            return Ok(Some((self.variable(content, true, len)?, len)));
         };
-        let token = match remainder.chars().next() {
-            Some('#') => Token::SectionStart(remainder[1..content_len].trim(), Variant::Direct),
-            Some('^') => Token::SectionStart(remainder[1..content_len].trim(), Variant::Inverse),
-            Some('/') => Token::SectionEnd(remainder[1..content_len].trim()),
-            Some('>') => Token::Partial(remainder[1..content_len].trim(), String::new()),
-            Some('&') => Token::Variable(remainder[1..content_len].trim(), false),
-            Some('!') => Token::Comment,
-            _ => Token::Variable(remainder[..content_len].trim(), true),
+        let rest = &content[sigil.len_utf8()..];
+        let sigils = &self.sigils;
+        let token = match sigil {
+            _ if sigil == sigils.section => Token::SectionStart(rest.trim(), Variant::Direct),
+            _ if sigil == sigils.inverted => Token::SectionStart(rest.trim(), Variant::Inverse),
+            _ if sigil == sigils.close => Token::SectionEnd(rest.trim()),
+            _ if sigil == sigils.partial => Token::Partial(rest.trim(), String::new()),
+            _ if sigil == sigils.unescaped => self.variable(rest, false, len)?,
+            _ if sigil == sigils.comment => Token::Comment,
+            _ if sigil == sigils.pragma => self.pragma(rest, len)?,
+            _ if self.extensions.contains(&sigil) => Token::Extension(sigil, rest.trim()),
+            _ => self.variable(content, true, len)?,
         };
-        let total_delim_len = self.open_delim.len() + self.close_delim.len();
-        Ok(Some((token, content_len + total_delim_len)))
+        Ok(Some((token, len)))
+    }
+
+    /// Splits the content of a pragma tag into its name and `key=value`
+    /// options. Pragmas may only follow whitespace, comments and other
+    /// pragmas.
+    fn pragma(&self, content: &'t str, len: usize) -> Result<Token<'t>> {
+        let mut words = content.split_whitespace();
+        let name = words.next().filter(|_| self.preamble);
+        let options: Option<Vec<_>> = words
+            .map(|word| {
+                word.split_once('=')
+                    .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+            })
+            .collect();
+        match (name, options) {
+            (Some(name), Some(options)) => Ok(Token::Pragma(Pragma {
+                name,
+                options,
+                span: Span::new(self.pos, self.pos + len),
+            })),
+            _ => Err(self.error(len)),
+        }
+    }
+
+    /// Removes the whitespace control markers from the content of a tag.
+    fn strip_markers(&self, content: &'t str) -> &'t str {
+        if !self.whitespace_control {
+            return content;
+        }
+        let content = content.strip_prefix('~').unwrap_or(content);
+        content.strip_suffix('~').unwrap_or(content)
+    }
+
+    /// Returns the whitespace control markers of a token just scanned, which
+    /// must be read before any later tag changes the delimiters.
+    fn markers(&self, token: &Token, span: Span) -> Markers {
+        use Token::*;
+        if !self.whitespace_control
+            || matches!(token, Text(_) | Newline(_) | Whitespace(_) | SetDelim(..))
+        {
+            return Markers::default();
+        }
+        let tag = &self.text[span.start..span.end];
+        let inner = &tag[self.open_delim.len()..tag.len() - self.close_delim.len()];
+        Markers {
+            before: inner.starts_with('~'),
+            after: inner.ends_with('~'),
+        }
+    }
+
+    /// Splits the content of a variable tag into the name and, when filters
+    /// are enabled, the filters that follow it.
+    fn variable(&self, content: &'t str, escaped: bool, len: usize) -> Result<Token<'t>> {
+        if !self.filters || !content.contains('|') {
+            return Ok(Token::Variable(content.trim(), escaped, Vec::new()));
+        }
+        let mut parts = Vec::new();
+        let mut start = 0;
+        let mut quoted = false;
+        for (i, c) in content.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                '|' if !quoted => {
+                    parts.push(&content[start..i]);
+                    start = i + 1;
+                }
+                _ => (),
+            }
+        }
+        parts.push(&content[start..]);
+        let filters = match quoted {
+            true => None,
+            false => parts[1..].iter().map(|part| Self::filter(part)).collect(),
+        };
+        let Some(filters) = filters else {
+            return Err(self.error(len));
+        };
+        Ok(Token::Variable(parts[0].trim(), escaped, filters))
+    }
+
+    /// Parses a filter name followed by its arguments, returning `None` if
+    /// the name is missing or a quote is not closed.
+    fn filter(text: &'t str) -> Option<Filter<'t>> {
+        let mut words = Vec::new();
+        let mut rest = text.trim_start();
+        while !rest.is_empty() {
+            let (word, after) = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"')?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            words.push(word);
+            rest = after.trim_start();
+        }
+        let (name, args) = words.split_first()?;
+        if name.is_empty() || text.trim_start().starts_with('"') {
+            return None;
+        }
+        Some(Filter {
+            name,
+            args: args.to_vec(),
+        })
     }
 
     fn scan_newline(&self) -> Option<(Token<'t>, usize)> {
         match self.remainder().strip_prefix("\r\n") {
             Some(_) => Some((Token::Newline("\r\n"), 2)),
-            None => match self.remainder().strip_prefix("\n") {
-                Some(_) => Some((Token::Newline("\n"), 1)),
-                None => None,
-            },
+            None => self
+                .remainder()
+                .strip_prefix("\n")
+                .map(|_| (Token::Newline("\n"), 1)),
         }
     }
 
@@ -212,25 +482,261 @@ impl<'t> Lexer<'t> {
         }
     }
 
-    fn scan_text(&self) -> (Token<'t>, usize) {
-        let mut len = 0;
-        while len < self.remainder().len() {
-            let text = &self.remainder()[len..];
-            if text.starts_with(&self.open_delim)
-                || text.starts_with("\r\n")
-                || text.starts_with("\n")
-            {
+    /// Scans text up to the next opening delimiter or newline. Both are found
+    /// with a substring search, and the position of the next delimiter is
+    /// kept so that text spanning many lines is only searched once.
+    fn scan_text(&mut self) -> (Token<'t>, usize) {
+        let remainder = self.remainder();
+        let delim = self.next_open() - self.pos;
+        let len = match remainder[..delim].find('\n') {
+            Some(pos) if remainder[..pos].ends_with('\r') => pos - 1,
+            Some(pos) => pos,
+            None => delim,
+        };
+        (Token::Text(&remainder[..len]), len)
+    }
+
+    /// Finds the next opening delimiter, or the next verbatim block if the
+    /// delimiters have been changed, since verbatim blocks always start with
+    /// braces.
+    fn next_open(&mut self) -> usize {
+        match self.next_open {
+            Some(pos) if pos >= self.pos => pos,
+            _ => {
+                let remainder = self.remainder();
+                let mut offset = remainder.find(&*self.open_delim);
+                if self.open_delim != "{{" {
+                    if let Some(raw) = remainder.find("{{{{") {
+                        offset = Some(offset.map_or(raw, |offset| offset.min(raw)));
+                    }
+                }
+                let pos = offset.map_or(self.text.len(), |offset| self.pos + offset);
+                self.next_open = Some(pos);
+                pos
+            }
+        }
+    }
+}
+
+/// Whether a tag trims the whitespace before and after it, written as `~`
+/// just inside its delimiters.
+#[derive(Debug, Clone, Copy, Default)]
+struct Markers {
+    before: bool,
+    after: bool,
+}
+
+/// The tokens of a template with whitespace around standalone tags removed.
+/// Only the tokens of the line being scanned are held at any time, so the
+/// whole template is processed in a single pass. With whitespace control,
+/// trailing whitespace is also held until it is clear whether a later tag
+/// trims it.
+pub struct Tokens<'t> {
+    lexer: Lexer<'t>,
+    line: VecDeque<(Token<'t>, Span, Markers)>,
+    /// Tokens whose markers have been applied, waiting to be returned.
+    held: VecDeque<(Token<'t>, Span)>,
+    /// Whether the last tag trims the whitespace after it.
+    trim_next: bool,
+    done: bool,
+}
+
+impl<'t> Iterator for Tokens<'t> {
+    type Item = Result<(Token<'t>, Span)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.lexer.whitespace_control {
+            // A pragma may have switched whitespace control off while tokens
+            // were held.
+            if let Some(token) = self.held.pop_front() {
+                return Some(Ok(token));
+            }
+            if self.line.is_empty() {
+                if let Err(error) = self.scan_line() {
+                    return Some(Err(error));
+                }
+            }
+            return self
+                .line
+                .pop_front()
+                .map(|(token, span, _)| Ok((token, span)));
+        }
+        while !self.done && self.settled() == 0 {
+            if let Err(error) = self.scan_line() {
+                return Some(Err(error));
+            }
+            self.done = self.line.is_empty();
+            self.apply_markers();
+        }
+        self.held.pop_front().map(Ok)
+    }
+}
+
+impl<'t> Tokens<'t> {
+    fn scan_line(&mut self) -> Result<()> {
+        while let Some((token, span)) = self.lexer.next_token()? {
+            let newline = matches!(token, Token::Newline(_));
+            let markers = self.lexer.markers(&token, span);
+            self.line.push_back((token, span, markers));
+            if newline {
                 break;
             }
-            len += text.chars().next().unwrap().len_utf8();
         }
-        (Token::Text(self.remainder()[..len].into()), len)
+        Self::strip_standalone_whitespace(&mut self.line);
+        Ok(())
+    }
+
+    /// Moves the tokens of the line to the held tokens, trimming whitespace
+    /// next to the tags with markers.
+    fn apply_markers(&mut self) {
+        while let Some((mut token, span, markers)) = self.line.pop_front() {
+            if markers.before {
+                self.trim_held();
+                // A standalone partial is not indented by the whitespace
+                // the marker trims.
+                if let Token::Partial(_, indent) = &mut token {
+                    indent.clear();
+                }
+            }
+            let token = match self.trim_next {
+                true => match Self::trim_start(token, span) {
+                    Some(token) => token,
+                    None => continue,
+                },
+                false => (token, span),
+            };
+            self.trim_next = markers.after;
+            self.held.push_back(token);
+        }
+    }
+
+    /// Removes whitespace from the end of the held tokens, up to the last
+    /// tag or text that is not whitespace.
+    fn trim_held(&mut self) {
+        while let Some((token, span)) = self.held.pop_back() {
+            let text = match token {
+                Token::Text(text) if !Self::is_verbatim(text, span) => text,
+                token if Self::is_blank(&token, span) => continue,
+                token => {
+                    self.held.push_back((token, span));
+                    return;
+                }
+            };
+            let trimmed = text.trim_end();
+            if !trimmed.is_empty() {
+                let span = Span::new(span.start, span.start + trimmed.len());
+                self.held.push_back((Token::Text(trimmed), span));
+                return;
+            }
+        }
+    }
+
+    /// Removes whitespace from the start of a token, returning `None` if
+    /// nothing is left of it.
+    fn trim_start(token: Token<'t>, span: Span) -> Option<(Token<'t>, Span)> {
+        match token {
+            Token::Whitespace(_) | Token::Newline(_) => None,
+            Token::Text(text) if !Self::is_verbatim(text, span) => {
+                let trimmed = text.trim_start();
+                let start = span.end - trimmed.len();
+                (!trimmed.is_empty()).then(|| (Token::Text(trimmed), Span::new(start, span.end)))
+            }
+            token => Some((token, span)),
+        }
+    }
+
+    /// Returns the number of held tokens that no later marker can change:
+    /// those up to the last tag, or up to the last text that is not blank,
+    /// whose end a marker could still trim.
+    fn settled(&self) -> usize {
+        if self.done {
+            return self.held.len();
+        }
+        let last = self
+            .held
+            .iter()
+            .rposition(|(token, span)| !Self::is_blank(token, *span));
+        match last.map(|pos| (pos, &self.held[pos])) {
+            Some((pos, (Token::Text(text), span))) if !Self::is_verbatim(text, *span) => pos,
+            Some((pos, _)) => pos + 1,
+            None => 0,
+        }
+    }
+
+    /// Whether a text token is the content of a verbatim block, which markers
+    /// leave as it is. Its span covers the tags around the content as well.
+    fn is_verbatim(text: &str, span: Span) -> bool {
+        span.end - span.start != text.len()
+    }
+
+    /// Whether a token is whitespace that markers trim entirely.
+    fn is_blank(token: &Token, span: Span) -> bool {
+        match token {
+            Token::Whitespace(_) | Token::Newline(_) => true,
+            Token::Text(text) => text.trim().is_empty() && !Self::is_verbatim(text, span),
+            _ => false,
+        }
+    }
+
+    fn strip_standalone_whitespace(line: &mut VecDeque<(Token<'t>, Span, Markers)>) {
+        let contains_text_or_var = line.iter().any(|(x, ..)| Self::is_text_or_var(x));
+        let special_tag_count = line
+            .iter()
+            .filter(|(x, ..)| Self::is_special_tag(x))
+            .count();
+        if contains_text_or_var || special_tag_count != 1 {
+            return;
+        }
+
+        let tag_pos = line
+            .iter()
+            .position(|(x, ..)| Self::is_special_tag(x))
+            .unwrap();
+        if matches!(line[tag_pos].0, Token::Partial(..)) {
+            let mut indent = String::new();
+            for (token, ..) in line.range(..tag_pos) {
+                let Token::Whitespace(ws) = token else {
+                    unreachable!();
+                };
+                indent.push_str(ws);
+            }
+            let Token::Partial(_, partial_indent) = &mut line[tag_pos].0 else {
+                unreachable!();
+            };
+            *partial_indent = indent;
+        }
+
+        line.drain(..tag_pos);
+        line.truncate(1);
+    }
+
+    fn is_text_or_var(token: &Token) -> bool {
+        use Token::*;
+        matches!(token, Text(_) | Variable(..))
+    }
+
+    fn is_special_tag(token: &Token) -> bool {
+        use Token::*;
+        matches!(
+            token,
+            SectionStart(..)
+                | SectionEnd(_)
+                | Partial(..)
+                | SetDelim(..)
+                | Comment
+                | Pragma(_)
+                | Extension(..)
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ast::Variant, error::Result};
+    use crate::{
+        ast::{self, Filter, Span, Variant},
+        error::Result,
+        options::Sigils,
+    };
 
     use super::{Lexer, Token::*};
 
@@ -284,7 +790,7 @@ mod tests {
         let text = "{{foo}}";
         let mut lexer = Lexer::new(text);
         let token = lexer.next()?;
-        assert_eq!(token, Some(Variable("foo", true)));
+        assert_eq!(token, Some(Variable("foo", true, Vec::new())));
         Ok(())
     }
 
@@ -293,7 +799,7 @@ mod tests {
         let text = "{{&foo}}";
         let mut lexer = Lexer::new(text);
         let token = lexer.next()?;
-        assert_eq!(token, Some(Variable("foo", false)));
+        assert_eq!(token, Some(Variable("foo", false, Vec::new())));
         Ok(())
     }
 
@@ -302,7 +808,26 @@ mod tests {
         let text = "{{{foo}}}";
         let mut lexer = Lexer::new(text);
         let token = lexer.next()?;
-        assert_eq!(token, Some(Variable("foo", false)));
+        assert_eq!(token, Some(Variable("foo", false, Vec::new())));
+        Ok(())
+    }
+
+    #[test]
+    fn filters() -> Result<()> {
+        let text = r#"{{{foo | truncate 3 " | " | upper}}}"#;
+        let mut lexer = Lexer::new(text).filters(true);
+        let token = lexer.next()?;
+        let filters = vec![
+            Filter {
+                name: "truncate",
+                args: vec!["3", " | "],
+            },
+            Filter {
+                name: "upper",
+                args: vec![],
+            },
+        ];
+        assert_eq!(token, Some(Variable("foo", false, filters)));
         Ok(())
     }
 
@@ -359,4 +884,71 @@ mod tests {
         assert_eq!(token, Some(SetDelim("//", "//")));
         Ok(())
     }
+
+    #[test]
+    fn configured_delimiters() -> Result<()> {
+        let sigils = Sigils {
+            section: '+',
+            ..Sigils::default()
+        };
+        let text = "«+a»«{b}»«=<% %>=»<%+c%>";
+        let mut lexer = Lexer::new(text).delimiters("«", "»").sigils(sigils);
+        assert_eq!(lexer.next()?, Some(SectionStart("a", Variant::Direct)));
+        assert_eq!(lexer.next()?, Some(Variable("b", false, Vec::new())));
+        assert_eq!(lexer.next()?, Some(SetDelim("<%", "%>")));
+        assert_eq!(lexer.next()?, Some(SectionStart("c", Variant::Direct)));
+        Ok(())
+    }
+
+    #[test]
+    fn pragma() -> Result<()> {
+        let text = "{{%FILTERS}}\n{{%STRICT sections=true}}{{a|b}}{{%STRICT}}";
+        let mut lexer = Lexer::new(text);
+        let pragma = |name, options, span| {
+            Pragma(ast::Pragma {
+                name,
+                options,
+                span,
+            })
+        };
+        let expected = pragma("FILTERS", vec![], Span::new(0, 12));
+        assert_eq!(lexer.next()?, Some(expected));
+        assert_eq!(lexer.next()?, Some(Newline("\n")));
+        let expected = pragma("STRICT", vec![("sections", "true")], Span::new(13, 38));
+        assert_eq!(lexer.next()?, Some(expected));
+        let filters = vec![Filter {
+            name: "b",
+            args: vec![],
+        }];
+        assert_eq!(lexer.next()?, Some(Variable("a", true, filters)));
+        assert!(lexer.next().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn raw_block() -> Result<()> {
+        let text = "{{{{raw}}}}{{a}}{{=<% %>=}}{{{{/raw}}}}{{b}}";
+        let mut lexer = Lexer::new(text);
+        assert_eq!(lexer.next()?, Some(Text("{{a}}{{=<% %>=}}")));
+        assert_eq!(lexer.next()?, Some(Variable("b", true, Vec::new())));
+        Ok(())
+    }
+
+    #[test]
+    fn whitespace_control() -> Result<()> {
+        let text = "a \n {{~b~}} \n c{{! d ~}}\n";
+        let lexer = Lexer::new(text).whitespace_control(true);
+        let tokens = lexer.tokens().collect::<Result<Vec<_>>>()?;
+        let tokens: Vec<_> = tokens.into_iter().map(|(token, _)| token).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Text("a"),
+                Variable("b", true, Vec::new()),
+                Text("c"),
+                Comment
+            ]
+        );
+        Ok(())
+    }
 }
@@ -1,10 +1,31 @@
-mod ast;
+pub mod ast;
 mod context;
+pub mod cst;
 mod error;
-mod lexer;
+mod extensions;
+mod filters;
+mod format;
+mod helpers;
+pub mod lexer;
+mod options;
 mod parser;
+mod pragma;
+mod program;
+mod references;
+mod render;
+mod schema;
 mod template;
+pub mod typed;
+pub mod visit;
 
+pub use ast::Position;
 pub use context::{Context, MapBuilder, VecBuilder};
-pub use error::Result;
-pub use template::Template;
+pub use error::{Error, Limit, Result};
+pub use extensions::Output;
+pub use format::{format, format_with_options, FormatOptions, UnescapedStyle};
+pub use helpers::Block;
+pub use options::{Backend, CompileOptions, RenderOptions, Sigils, Truthiness};
+pub use references::{Reference, ReferenceKind};
+pub use render::ContextResolver;
+pub use schema::{Issue, IssueKind, Schema};
+pub use template::{Partials, Template};
@@ -0,0 +1,87 @@
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use stache::{FormatOptions, UnescapedStyle};
+
+const USAGE: &str = "\
+usage: stache fmt [options] <file>...
+
+Formats Mustache templates in place.
+
+options:
+  --check       report files that are not formatted instead of rewriting them
+  --ampersand   write unescaped variables as {{&name}} instead of {{{name}}}
+  --padding     write tags as {{ name }} instead of {{name}}
+  --tabs        indent standalone tags with tabs instead of two spaces";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, args)) if command == "fmt" => fmt(args),
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn fmt(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut options = FormatOptions::new();
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--ampersand" => options = options.unescaped(UnescapedStyle::Ampersand),
+            "--padding" => options = options.padding(true),
+            "--tabs" => options = options.indent("\t"),
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::from(2);
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    let mut unformatted = false;
+    for file in files {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("{file}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match stache::format_with_options(&text, options.clone()) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                eprintln!("{file}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == text {
+            continue;
+        }
+        if check {
+            println!("{file}");
+            unformatted = true;
+        } else if let Err(error) = fs::write(file, formatted) {
+            eprintln!("{file}: {error}");
+            failed = true;
+        }
+    }
+
+    match (failed, unformatted) {
+        (true, _) => ExitCode::from(2),
+        (false, true) => ExitCode::FAILURE,
+        (false, false) => ExitCode::SUCCESS,
+    }
+}
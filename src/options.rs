@@ -0,0 +1,422 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::context::Context;
+use crate::error::{Error, Result};
+use crate::extensions::{Extension, Extensions, Output};
+use crate::filters::Filters;
+use crate::helpers::{Block, Helpers};
+use crate::render::ContextResolver;
+
+/// How deeply sections may nest in a template and while rendering unless the
+/// options say otherwise.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+/// How a compiled template is rendered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Backend {
+    /// Walks the syntax tree recursively.
+    #[default]
+    Tree,
+    /// Lowers the syntax tree into a flat list of instructions once, which a
+    /// small interpreter then runs for every render.
+    Program,
+}
+
+/// The rules that decide which values are truthy, rendering the body of a
+/// section, and which are falsy, rendering the body of an inverted section.
+/// Missing values are always falsy.
+#[derive(Clone, Default)]
+pub enum Truthiness {
+    /// Only `false`, null and empty lists are falsy, as in the Mustache spec
+    /// and Ruby.
+    #[default]
+    Spec,
+    /// The empty string, zero and NaN are falsy as well, as in JavaScript.
+    JavaScript,
+    /// The empty string, zero and empty maps are falsy as well, as in Python.
+    Python,
+    /// Values for which the predicate returns `true` are truthy.
+    Custom(Arc<dyn Fn(&Context) -> bool + Send + Sync>),
+}
+
+impl Truthiness {
+    /// Creates rules from a predicate that returns whether a value is truthy.
+    pub fn custom<F>(predicate: F) -> Self
+    where
+        F: Fn(&Context) -> bool + Send + Sync + 'static,
+    {
+        Truthiness::Custom(Arc::new(predicate))
+    }
+
+    /// Whether a value is truthy under these rules.
+    pub fn is_truthy(&self, value: &Context) -> bool {
+        match (self, value) {
+            (Truthiness::Custom(predicate), _) => predicate(value),
+            (Truthiness::JavaScript | Truthiness::Python, Context::String(text)) => {
+                !text.is_empty()
+            }
+            (Truthiness::JavaScript | Truthiness::Python, Context::Integer(number)) => *number != 0,
+            (Truthiness::JavaScript, Context::Float(number)) => *number != 0.0 && !number.is_nan(),
+            (Truthiness::Python, Context::Float(number)) => *number != 0.0,
+            (Truthiness::Python, Context::Map(map)) => !map.is_empty(),
+            _ => value.is_truthy(),
+        }
+    }
+}
+
+impl fmt::Debug for Truthiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Truthiness::Spec => write!(f, "Spec"),
+            Truthiness::JavaScript => write!(f, "JavaScript"),
+            Truthiness::Python => write!(f, "Python"),
+            Truthiness::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Settings that control how a template is compiled.
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    pub(crate) max_size: Option<usize>,
+    pub(crate) max_nesting: usize,
+    pub(crate) backend: Backend,
+    pub(crate) filters: bool,
+    pub(crate) helpers: bool,
+    pub(crate) whitespace_control: bool,
+    pub(crate) list_indexing: bool,
+    pub(crate) delimiters: Option<(String, String)>,
+    pub(crate) sigils: Sigils,
+    pub(crate) extensions: Extensions,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_nesting: MAX_DEPTH,
+            backend: Backend::default(),
+            filters: false,
+            helpers: false,
+            whitespace_control: false,
+            list_indexing: false,
+            delimiters: None,
+            sigils: Sigils::default(),
+            extensions: Extensions::default(),
+        }
+    }
+}
+
+impl CompileOptions {
+    pub fn new() -> Self {
+        CompileOptions::default()
+    }
+
+    /// Sets the maximum size of the template source in bytes.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Sets how deeply sections may nest within the template source. Defaults
+    /// to 256, as [`RenderOptions::max_depth`] does, so that deeply nested
+    /// input fails to compile instead of overflowing the stack.
+    pub fn max_nesting(mut self, depth: usize) -> Self {
+        self.max_nesting = depth;
+        self
+    }
+
+    /// Sets how the template and the partials it includes are rendered.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets whether variable tags may pass their value through filters, as in
+    /// `{{title | upper | truncate 20}}`. Disabled by default, in which case
+    /// `|` is part of the name.
+    pub fn filters(mut self, enabled: bool) -> Self {
+        self.filters = enabled;
+        self
+    }
+
+    /// Sets whether section tags may pass arguments to a helper, as in
+    /// `{{#if_eq status "paid"}}`. Disabled by default. A section with
+    /// arguments calls the helper its name refers to, which is closed by the
+    /// name alone, as in `{{/if_eq}}`.
+    pub fn helpers(mut self, enabled: bool) -> Self {
+        self.helpers = enabled;
+        self
+    }
+
+    /// Sets whether `~` just inside the delimiters of a tag, as in
+    /// `{{~#items~}}`, removes the whitespace and newlines on that side of
+    /// the tag, up to the nearest other text or tag. Disabled by default.
+    pub fn whitespace_control(mut self, enabled: bool) -> Self {
+        self.whitespace_control = enabled;
+        self
+    }
+
+    /// Sets whether dotted names may reach into lists by index, as in
+    /// `{{items.0.name}}` or `{{items.-1.name}}` for the last item, and take
+    /// the length of lists and strings, as in `{{items.length}}`. Disabled by
+    /// default, in which case such lookups find nothing, as the spec has it.
+    pub fn list_indexing(mut self, enabled: bool) -> Self {
+        self.list_indexing = enabled;
+        self
+    }
+
+    /// Sets the delimiters templates start with instead of `{{` and `}}`.
+    /// Set-delimiter tags still change them, and triple mustaches add `{`
+    /// and `}` inside them, as in `<%{name}%>`.
+    ///
+    /// Fails with [`Error::Syntax`] if either delimiter is empty or contains
+    /// whitespace or `=`, which a set-delimiter tag could not express.
+    pub fn delimiters(mut self, open: &str, close: &str) -> Result<Self> {
+        for delimiter in [open, close] {
+            if delimiter.is_empty() || delimiter.contains(|c: char| c.is_whitespace() || c == '=') {
+                return Err(Error::Syntax(format!("invalid delimiter {delimiter:?}")));
+            }
+        }
+        self.delimiters = Some((open.into(), close.into()));
+        Ok(self)
+    }
+
+    /// Sets the characters that mark the kind of a tag, such as `#` for a
+    /// section.
+    ///
+    /// Fails with [`Error::Syntax`] if two kinds of tag, including those of
+    /// extensions, share a character, or if one of them is whitespace, `=` or
+    /// `{`, which already have a meaning after the opening delimiter.
+    pub fn sigils(mut self, sigils: Sigils) -> Result<Self> {
+        let chars = sigils.chars();
+        for (i, c) in chars.iter().enumerate() {
+            check_sigil(*c)?;
+            if chars[..i].contains(c) || self.extensions.get(*c).is_some() {
+                return Err(Error::Syntax(format!("sigil {c:?} is used twice")));
+            }
+        }
+        self.sigils = sigils;
+        Ok(self)
+    }
+
+    /// Registers an extension for tags that start with a sigil, as in
+    /// `{{@icon star}}` for the sigil `@`. When the template is compiled,
+    /// `parse` turns the content of each such tag after the sigil into a
+    /// node, or returns a message explaining why the content is malformed.
+    /// When it is rendered, `render` writes the node to the output, looking
+    /// up names in the context as it needs. Tags alone on their line are
+    /// standalone, like section tags.
+    ///
+    /// Fails with [`Error::Syntax`] if the sigil already marks another kind of
+    /// tag, including the tags of another extension, or is whitespace, `=` or
+    /// `{`.
+    pub fn extension<T, P, R>(mut self, sigil: char, parse: P, render: R) -> Result<Self>
+    where
+        T: Send + Sync + 'static,
+        P: Fn(&str) -> std::result::Result<T, String> + Send + Sync + 'static,
+        R: Fn(&T, &ContextResolver, &mut Output) -> Result<()> + Send + Sync + 'static,
+    {
+        check_sigil(sigil)?;
+        if self.sigils.chars().contains(&sigil) || self.extensions.get(sigil).is_some() {
+            return Err(Error::Syntax(format!("sigil {sigil:?} is used twice")));
+        }
+        let parse = move |content: &str| {
+            let node = parse(content)?;
+            Ok(Arc::new(node) as Arc<dyn Any + Send + Sync>)
+        };
+        let render =
+            move |node: &(dyn Any + Send + Sync), context: &ContextResolver, out: &mut Output| {
+                match node.downcast_ref::<T>() {
+                    Some(node) => render(node, context, out),
+                    None => Err(Error::Extension {
+                        sigil,
+                        message: "the node was parsed by another extension".into(),
+                    }),
+                }
+            };
+        let extension = Extension {
+            parse: Arc::new(parse),
+            render: Arc::new(render),
+        };
+        self.extensions.insert(sigil, extension);
+        Ok(self)
+    }
+}
+
+/// Fails if a character cannot mark the kind of a tag, because it already has
+/// a meaning after the opening delimiter.
+fn check_sigil(c: char) -> Result<()> {
+    match c.is_whitespace() || matches!(c, '=' | '{') {
+        true => Err(Error::Syntax(format!("invalid sigil {c:?}"))),
+        false => Ok(()),
+    }
+}
+
+/// The characters that follow the opening delimiter to mark the kind of a tag.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Sigils {
+    /// Opens a section, `#` by default.
+    pub section: char,
+    /// Opens an inverted section, `^` by default.
+    pub inverted: char,
+    /// Closes a section, `/` by default.
+    pub close: char,
+    /// Includes a partial, `>` by default.
+    pub partial: char,
+    /// Writes a variable without escaping it, `&` by default.
+    pub unescaped: char,
+    /// Starts a comment, `!` by default.
+    pub comment: char,
+    /// Starts a pragma, `%` by default.
+    pub pragma: char,
+}
+
+impl Sigils {
+    fn chars(&self) -> [char; 7] {
+        [
+            self.section,
+            self.inverted,
+            self.close,
+            self.partial,
+            self.unescaped,
+            self.comment,
+            self.pragma,
+        ]
+    }
+}
+
+impl Default for Sigils {
+    fn default() -> Self {
+        Self {
+            section: '#',
+            inverted: '^',
+            close: '/',
+            partial: '>',
+            unescaped: '&',
+            comment: '!',
+            pragma: '%',
+        }
+    }
+}
+
+/// Settings that control how a template is rendered.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub(crate) max_depth: usize,
+    pub(crate) max_output: Option<usize>,
+    pub(crate) max_iterations: Option<usize>,
+    pub(crate) max_partials: Option<usize>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) cancel: Option<Arc<AtomicBool>>,
+    pub(crate) filters: Filters,
+    pub(crate) helpers: Helpers,
+    pub(crate) truthiness: Truthiness,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            max_output: None,
+            max_iterations: None,
+            max_partials: None,
+            deadline: None,
+            cancel: None,
+            filters: Filters::default(),
+            helpers: Helpers::default(),
+            truthiness: Truthiness::default(),
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        RenderOptions::default()
+    }
+
+    /// Sets how deeply sections and partials may nest while rendering.
+    /// Exceeding the limit, for example with a partial that includes itself
+    /// unconditionally, fails the render instead of overflowing the stack.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets the maximum size of the rendered output in bytes.
+    pub fn max_output(mut self, bytes: usize) -> Self {
+        self.max_output = Some(bytes);
+        self
+    }
+
+    /// Sets the maximum number of times section bodies may be rendered, counted
+    /// across the whole render.
+    pub fn max_iterations(mut self, iterations: usize) -> Self {
+        self.max_iterations = Some(iterations);
+        self
+    }
+
+    /// Sets the maximum number of partials that may be expanded, counted across
+    /// the whole render.
+    pub fn max_partials(mut self, expansions: usize) -> Self {
+        self.max_partials = Some(expansions);
+        self
+    }
+
+    /// Sets a point in time after which the render is abandoned.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets a flag that abandons the render once it is raised, for example
+    /// from another thread.
+    pub fn cancel(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    /// Registers a filter, replacing any built-in filter of the same name.
+    /// The filter receives the value so far, `None` if the name was not
+    /// found, and the arguments written after the filter's name, and returns
+    /// the new value or a message explaining why it failed.
+    pub fn filter<F>(mut self, name: &str, filter: F) -> Self
+    where
+        F: Fn(Option<&Context>, &[&str]) -> std::result::Result<Option<Context>, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.filters.insert(name, Arc::new(filter));
+        self
+    }
+
+    /// Registers a helper, replacing any built-in helper of the same name.
+    /// Sections with arguments call the helper their name refers to, which
+    /// decides through the [`Block`] it is given whether, how often and with
+    /// which context the body of the section renders.
+    pub fn helper<F>(mut self, name: &str, helper: F) -> Self
+    where
+        F: Fn(&mut Block) -> Result<()> + Send + Sync + 'static,
+    {
+        self.helpers.insert(name, Arc::new(helper));
+        self
+    }
+
+    /// Sets which values render the body of a section and which render the
+    /// body of an inverted section instead.
+    pub fn truthiness(mut self, truthiness: Truthiness) -> Self {
+        self.truthiness = truthiness;
+        self
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
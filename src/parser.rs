@@ -1,75 +1,234 @@
 #![allow(dead_code)]
 
-use std::slice::Iter;
-
 use crate::{
-    ast::{Node, Partial, Root, Section, Variable, Variant},
-    error::{Error, Result},
-    lexer::{Lexer, Token},
+    ast::{
+        Argument, ArgumentValue, Extension, Filter, Name, Node, Partial, Pragma, Root, Section,
+        Span, Text, Variable, Variant,
+    },
+    error::{Error, Limit, Result},
+    extensions::Extensions,
+    lexer::{Lexer, Token, Tokens},
+    options::CompileOptions,
+    pragma,
 };
 
 pub struct Parser<'t> {
-    lexer: Lexer<'t>,
+    text: &'t str,
+    tokens: Tokens<'t>,
+    max_nesting: usize,
+    helpers: bool,
+    /// Whether names starting with a single `.` are anchored to the innermost
+    /// context, as the `ANCHORED-DOT` pragma makes them.
+    anchored_dot: bool,
+    extensions: Extensions,
 }
 
 impl<'t> Parser<'t> {
-    fn new(text: &'t str) -> Self {
+    fn new(text: &'t str, options: &CompileOptions) -> Self {
+        let mut lexer = Lexer::new(text)
+            .filters(options.filters)
+            .whitespace_control(options.whitespace_control)
+            .sigils(options.sigils)
+            .extensions(options.extensions.sigils());
+        if let Some((open, close)) = &options.delimiters {
+            lexer = lexer.delimiters(open, close);
+        }
         Self {
-            lexer: Lexer::new(text),
+            text,
+            tokens: lexer.tokens(),
+            max_nesting: options.max_nesting,
+            helpers: options.helpers,
+            anchored_dot: false,
+            extensions: options.extensions.clone(),
         }
     }
 
-    pub fn parse(text: &'t str) -> Result<Node> {
-        let mut parser = Parser::new(text);
+    pub fn parse(text: &'t str, options: &CompileOptions) -> Result<Node<'t>> {
+        if options.max_size.is_some_and(|max| text.len() > max) {
+            return Err(Error::Limit(Limit::Size));
+        }
+        let mut parser = Parser::new(text, options);
         parser.root()
     }
 
     fn root(&mut self) -> Result<Node<'t>> {
-        let tokens = self.lexer.tokens()?;
-        let mut token_it = tokens.iter();
-        let mut root = Node::Root(Root::default());
-        while let Some(token) = token_it.next() {
+        let mut root = Root::default();
+        while let Some((token, span)) = self.tokens.next().transpose()? {
             let node = match token {
                 Token::Comment => continue,
                 Token::SetDelim(..) => continue,
-                Token::Text(x) => Node::Text(x),
-                Token::Whitespace(x) => Node::Text(x),
-                Token::Newline(x) => Node::Text(x),
-                Token::Variable(name, esc) => Node::Variable(Variable::new(name, *esc)),
-                Token::Partial(name, indent) => Node::Partial(Partial::new(name, indent.clone())),
-                Token::SectionStart(name, variant) => {
-                    Self::section(&name, &variant, &mut token_it)?
+                Token::Pragma(pragma) => {
+                    self.pragma(&pragma)?;
+                    root.pragmas.push(pragma);
+                    continue;
                 }
-                Token::SectionEnd(..) => return Err(Error::Parse),
+                Token::Text(x) => Node::Text(Text::new(x, span)),
+                Token::Whitespace(x) => Node::Text(Text::new(x, span)),
+                Token::Newline(x) => Node::Text(Text::new(x, span)),
+                Token::Variable(name, esc, filters) => self.variable(name, esc, filters, span)?,
+                Token::Partial(name, indent) => self.partial(name, indent, span)?,
+                Token::Extension(sigil, content) => self.extension(sigil, content, span)?,
+                Token::SectionStart(name, variant) => self.section(name, variant, span, 1)?,
+                Token::SectionEnd(..) => return Err(Error::parse(self.text, span)),
             };
-            root.push(node);
+            root.children.push(node);
         }
-        Ok(root)
+        Ok(Node::Root(root))
+    }
+
+    /// Checks a pragma, switching the settings of the parser that it changes
+    /// for the rest of the template.
+    fn pragma(&mut self, pragma: &Pragma) -> Result<()> {
+        pragma::check(self.text, pragma)?;
+        match pragma.name {
+            "HELPERS" => self.helpers = pragma.enabled(),
+            "ANCHORED-DOT" => self.anchored_dot = pragma.enabled(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Parses the content of a tag with the extension registered for its
+    /// sigil.
+    fn extension(&self, sigil: char, content: &'t str, span: Span) -> Result<Node<'t>> {
+        let extension = self
+            .extensions
+            .get(sigil)
+            .expect("the lexer only scans the sigils of extensions");
+        let node =
+            (extension.parse)(content).map_err(|message| Error::Extension { sigil, message })?;
+        Ok(Node::Extension(Extension {
+            sigil,
+            content,
+            node,
+            span,
+        }))
     }
 
     fn section(
+        &mut self,
         name: &'t str,
-        variant: &Variant,
-        token_it: &mut Iter<Token<'t>>,
+        variant: Variant,
+        span: Span,
+        depth: usize,
     ) -> Result<Node<'t>> {
-        let mut section = Node::Section(Section::new(name.into(), *variant));
-        while let Some(token) = token_it.next() {
+        if depth > self.max_nesting {
+            return Err(Error::Limit(Limit::Nesting));
+        }
+        let (name, args) = self.arguments(name, span)?;
+        if variant == Variant::Inverse && !args.is_empty() {
+            return Err(Error::parse(self.text, span));
+        }
+        let mut section = Section::new(self.name(name, span)?, variant, span);
+        section.args = args;
+        while let Some((token, span)) = self.tokens.next().transpose()? {
             let node = match token {
                 Token::Comment => continue,
                 Token::SetDelim(..) => continue,
-                Token::Text(x) => Node::Text(x),
-                Token::Whitespace(x) => Node::Text(x),
-                Token::Newline(x) => Node::Text(x),
-                Token::Variable(name, esc) => Node::Variable(Variable::new(name, *esc)),
-                Token::Partial(name, indent) => Node::Partial(Partial::new(name, indent.clone())),
-                Token::SectionStart(name, variant) => Self::section(&name, &variant, token_it)?,
-                Token::SectionEnd(end_name) => match end_name == &name {
-                    true => break,
-                    false => return Err(Error::Parse),
+                // The lexer only accepts pragmas before any section.
+                Token::Pragma(pragma) => return Err(Error::parse(self.text, pragma.span)),
+                Token::Text(x) => Node::Text(Text::new(x, span)),
+                Token::Whitespace(x) => Node::Text(Text::new(x, span)),
+                Token::Newline(x) => Node::Text(Text::new(x, span)),
+                Token::Variable(name, esc, filters) => self.variable(name, esc, filters, span)?,
+                Token::Partial(name, indent) => self.partial(name, indent, span)?,
+                Token::Extension(sigil, content) => self.extension(sigil, content, span)?,
+                Token::SectionStart(name, variant) => {
+                    self.section(name, variant, span, depth + 1)?
+                }
+                Token::SectionEnd(end_name) => match end_name == name {
+                    true => {
+                        section.close = span;
+                        return Ok(Node::Section(section));
+                    }
+                    false => return Err(Error::parse(self.text, span)),
                 },
             };
-            section.push(node);
+            section.children.push(node);
         }
-        Ok(section)
+        // The tokens ran out before the section was closed.
+        Err(Error::parse(self.text, section.open))
+    }
+
+    fn variable(
+        &self,
+        name: &'t str,
+        escaped: bool,
+        filters: Vec<Filter<'t>>,
+        span: Span,
+    ) -> Result<Node<'t>> {
+        let mut variable = Variable::new(self.name(name, span)?, escaped, span);
+        variable.filters = filters;
+        Ok(Node::Variable(variable))
+    }
+
+    /// Parses a partial tag. A name starting with `*` is looked up while
+    /// rendering to find the partial to include.
+    fn partial(&self, name: &'t str, indent: String, span: Span) -> Result<Node<'t>> {
+        let Some(dynamic) = name.strip_prefix('*') else {
+            return Ok(Node::Partial(Partial::new(name, indent, span)));
+        };
+        let name = dynamic.trim_start();
+        let mut partial = Partial::new(name, indent, span);
+        partial.dynamic = Some(self.name(name, span)?);
+        Ok(Node::Partial(partial))
+    }
+
+    /// Splits the content of a section tag into the name and, when helpers are
+    /// enabled, the arguments that follow it.
+    fn arguments(&self, content: &'t str, span: Span) -> Result<(&'t str, Vec<Argument<'t>>)> {
+        let split = content.split_once(char::is_whitespace);
+        let Some((name, mut rest)) = split.filter(|_| self.helpers) else {
+            return Ok((content, Vec::new()));
+        };
+        let mut args = Vec::new();
+        rest = rest.trim_start();
+        while !rest.is_empty() {
+            let end = rest.find(|c: char| c == '=' || c == '"' || c.is_whitespace());
+            let key = match end {
+                Some(end) if rest[end..].starts_with('=') => {
+                    let key = &rest[..end];
+                    rest = &rest[end + 1..];
+                    Some(key)
+                }
+                _ => None,
+            };
+            let value = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted
+                        .find('"')
+                        .ok_or_else(|| Error::parse(self.text, span))?;
+                    rest = &quoted[end + 1..];
+                    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+                        return Err(Error::parse(self.text, span));
+                    }
+                    ArgumentValue::String(&quoted[..end])
+                }
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let word = &rest[..end];
+                    rest = &rest[end..];
+                    let number = word.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+                    match number && word.parse::<f64>().is_ok() {
+                        true => ArgumentValue::Number(word),
+                        false => ArgumentValue::Name(self.name(word, span)?),
+                    }
+                }
+            };
+            if key == Some("") || value == ArgumentValue::Name(Name::Key("")) {
+                return Err(Error::parse(self.text, span));
+            }
+            args.push(Argument { key, value });
+            rest = rest.trim_start();
+        }
+        Ok((name, args))
+    }
+
+    fn name(&self, name: &'t str, span: Span) -> Result<Name<'t>> {
+        let parsed = match self.anchored_dot {
+            true => Name::parse_anchored(name),
+            false => Name::parse(name),
+        };
+        parsed.ok_or_else(|| Error::name(self.text, name, span))
     }
 }
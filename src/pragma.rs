@@ -0,0 +1,81 @@
+//! Pragmas, which switch engine behavior for a single template, as in
+//! `{{%STRICT}}` at the top of the template.
+
+use crate::ast::Pragma;
+use crate::error::{Error, Result};
+use crate::options::Truthiness;
+
+/// An option of a pragma and the values it may take.
+type PragmaOption = (&'static str, &'static [&'static str]);
+
+const BOOL: &[&str] = &["true", "false"];
+
+const RULES: &[&str] = &["spec", "javascript", "python"];
+
+/// The pragmas a template may use, each with the options it accepts and the
+/// values each option may take.
+const PRAGMAS: &[(&str, &[PragmaOption])] = &[
+    ("FILTERS", &[("enabled", BOOL)]),
+    ("HELPERS", &[("enabled", BOOL)]),
+    ("WHITESPACE-CONTROL", &[("enabled", BOOL)]),
+    ("STRICT", &[("enabled", BOOL), ("sections", BOOL)]),
+    ("TRUTHINESS", &[("rules", RULES)]),
+    ("ANCHORED-DOT", &[("enabled", BOOL)]),
+];
+
+/// Checks that a pragma and its options are known.
+pub(crate) fn check(text: &str, pragma: &Pragma) -> Result<()> {
+    let error = |message: &str| Err(Error::pragma(text, pragma.name, message, pragma.span));
+    let Some((_, keys)) = PRAGMAS.iter().find(|(name, _)| *name == pragma.name) else {
+        return error("unknown pragma");
+    };
+    for (key, value) in &pragma.options {
+        let Some((_, values)) = keys.iter().find(|(name, _)| name == key) else {
+            return error(&format!("unknown option `{key}`"));
+        };
+        if !values.contains(value) {
+            let (last, rest) = values.split_last().unwrap();
+            let rest = rest
+                .iter()
+                .map(|value| format!("`{value}`"))
+                .collect::<Vec<_>>();
+            return error(&format!(
+                "expected `{key}` to be {} or `{last}`",
+                rest.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The settings the pragmas of a template make for rendering it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Settings {
+    /// Whether variables whose names are not found fail the render.
+    pub strict: bool,
+    /// Whether sections whose names are not found fail the render as well.
+    pub strict_sections: bool,
+    /// The truthiness rules that replace those of the render options.
+    pub truthiness: Option<Truthiness>,
+}
+
+impl Settings {
+    pub fn new(pragmas: &[Pragma]) -> Self {
+        let mut settings = Settings::default();
+        for pragma in pragmas {
+            if pragma.name == "STRICT" {
+                settings.strict = pragma.enabled();
+                settings.strict_sections =
+                    settings.strict && pragma.option("sections") == Some("true");
+            }
+            if pragma.name == "TRUTHINESS" {
+                settings.truthiness = Some(match pragma.option("rules") {
+                    Some("javascript") => Truthiness::JavaScript,
+                    Some("python") => Truthiness::Python,
+                    _ => Truthiness::Spec,
+                });
+            }
+        }
+        settings
+    }
+}
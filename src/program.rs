@@ -0,0 +1,124 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::ast::{Argument, Filter, Name, Node, Variant};
+
+/// A template lowered into a flat list of instructions. Sections become a
+/// pair of instructions around their body, with the indexes needed to skip
+/// the body or to repeat it for the next item of a list.
+#[derive(Debug)]
+pub(crate) struct Program<'t> {
+    instructions: Vec<Instruction<'t>>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Instruction<'t> {
+    /// Writes literal text.
+    Text(&'t str),
+    /// Looks up a name, passes its value through the filters and writes the
+    /// result, escaped or not.
+    Variable {
+        name: Name<'t>,
+        escaped: bool,
+        filters: Vec<Filter<'t>>,
+    },
+    /// Looks up a name and enters the body of the section, or continues at
+    /// `end` if the section does not render.
+    Section {
+        name: Name<'t>,
+        variant: Variant,
+        end: usize,
+    },
+    /// Calls the helper a section with arguments names, which renders the
+    /// instructions up to `end` as it sees fit, then continues at `end`.
+    Helper {
+        name: Name<'t>,
+        args: Vec<Argument<'t>>,
+        end: usize,
+    },
+    /// Closes the body of the section at `start`, jumping back to the start
+    /// of the body while a list has items left.
+    End { start: usize },
+    /// Renders a partial, indenting each of its lines. A dynamic partial
+    /// looks up the name of the partial first.
+    Partial {
+        name: &'t str,
+        dynamic: Option<Name<'t>>,
+        indent: String,
+    },
+    /// Renders the node an extension parsed.
+    Extension {
+        sigil: char,
+        node: Arc<dyn Any + Send + Sync>,
+    },
+}
+
+impl<'t> Program<'t> {
+    pub fn lower(root: &Node<'t>) -> Self {
+        let mut program = Self {
+            instructions: Vec::new(),
+        };
+        program.lower_node(root);
+        program
+    }
+
+    pub fn instructions(&self) -> &[Instruction<'t>] {
+        &self.instructions
+    }
+
+    fn lower_node(&mut self, node: &Node<'t>) {
+        match node {
+            Node::Root(root) => {
+                for child in &root.children {
+                    self.lower_node(child);
+                }
+            }
+            Node::Section(section) if !section.args.is_empty() => {
+                let start = self.instructions.len();
+                self.instructions.push(Instruction::Helper {
+                    name: section.name.clone(),
+                    args: section.args.clone(),
+                    end: 0,
+                });
+                for child in &section.children {
+                    self.lower_node(child);
+                }
+                let after = self.instructions.len();
+                if let Instruction::Helper { end, .. } = &mut self.instructions[start] {
+                    *end = after;
+                }
+            }
+            Node::Section(section) => {
+                let start = self.instructions.len();
+                self.instructions.push(Instruction::Section {
+                    name: section.name.clone(),
+                    variant: section.variant,
+                    end: 0,
+                });
+                for child in &section.children {
+                    self.lower_node(child);
+                }
+                self.instructions.push(Instruction::End { start });
+                let after = self.instructions.len();
+                if let Instruction::Section { end, .. } = &mut self.instructions[start] {
+                    *end = after;
+                }
+            }
+            Node::Variable(variable) => self.instructions.push(Instruction::Variable {
+                name: variable.name.clone(),
+                escaped: variable.escaped,
+                filters: variable.filters.clone(),
+            }),
+            Node::Partial(partial) => self.instructions.push(Instruction::Partial {
+                name: partial.name,
+                dynamic: partial.dynamic.clone(),
+                indent: partial.indent.clone(),
+            }),
+            Node::Text(text) => self.instructions.push(Instruction::Text(text.text)),
+            Node::Extension(extension) => self.instructions.push(Instruction::Extension {
+                sigil: extension.sigil,
+                node: extension.node.clone(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::ast::{ArgumentValue, LineIndex, Node, Position, Variant};
+use crate::error::Result;
+use crate::options::CompileOptions;
+use crate::template::{Partials, Template};
+
+/// A name that a template looks up in its context or among its partials.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub name: String,
+    /// Names of the sections enclosing the reference, outermost first. Sections
+    /// entered in an including template are listed before those of the partial.
+    pub path: Vec<String>,
+    /// The partial the reference appears in, or `None` for the template itself.
+    pub partial: Option<String>,
+    pub position: Position,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ReferenceKind {
+    Variable {
+        escaped: bool,
+    },
+    Section,
+    InvertedSection,
+    Partial,
+    /// The name looked up by `{{>*name}}` for the partial to include.
+    DynamicPartial,
+}
+
+/// Collects the references of a template and, when partials are given, of the
+/// partials it includes.
+pub(crate) struct Collector<'p> {
+    partials: Option<&'p Partials>,
+    options: &'p CompileOptions,
+    path: Vec<String>,
+    chain: Vec<String>,
+    references: Vec<Reference>,
+}
+
+impl<'p> Collector<'p> {
+    pub fn new(partials: Option<&'p Partials>, options: &'p CompileOptions) -> Self {
+        Self {
+            partials,
+            options,
+            path: Vec::new(),
+            chain: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+
+    pub fn collect(mut self, template: &Template) -> Result<Vec<Reference>> {
+        self.visit(template.root(), &LineIndex::new(template.text()))?;
+        Ok(self.references)
+    }
+
+    fn visit(&mut self, node: &Node, lines: &LineIndex) -> Result<()> {
+        match node {
+            Node::Root(root) => {
+                for child in &root.children {
+                    self.visit(child, lines)?;
+                }
+            }
+            // The name of a helper section is not a context key. Its named
+            // arguments are looked up instead, and its body is rendered in the
+            // enclosing scope.
+            Node::Section(section) if !section.args.is_empty() => {
+                for arg in &section.args {
+                    if let ArgumentValue::Name(name) = &arg.value {
+                        let kind = ReferenceKind::Variable { escaped: true };
+                        self.push(kind, name.to_string(), lines, section.open.start);
+                    }
+                }
+                for child in &section.children {
+                    self.visit(child, lines)?;
+                }
+            }
+            Node::Section(section) => {
+                let kind = match section.variant {
+                    Variant::Direct => ReferenceKind::Section,
+                    Variant::Inverse => ReferenceKind::InvertedSection,
+                };
+                let name = section.name.to_string();
+                self.push(kind, name.clone(), lines, section.open.start);
+                self.path.push(name);
+                for child in &section.children {
+                    self.visit(child, lines)?;
+                }
+                self.path.pop();
+            }
+            Node::Variable(variable) => {
+                let kind = ReferenceKind::Variable {
+                    escaped: variable.escaped,
+                };
+                self.push(kind, variable.name.to_string(), lines, variable.span.start);
+            }
+            Node::Partial(partial) if partial.dynamic.is_some() => {
+                let kind = ReferenceKind::DynamicPartial;
+                self.push(kind, partial.name.into(), lines, partial.span.start);
+            }
+            Node::Partial(partial) => {
+                self.push(
+                    ReferenceKind::Partial,
+                    partial.name.into(),
+                    lines,
+                    partial.span.start,
+                );
+                self.follow(partial.name)?;
+            }
+            Node::Text(_) | Node::Extension(_) => (),
+        }
+        Ok(())
+    }
+
+    /// Collects the references of an included partial. Partials that are
+    /// already being followed are not followed again, so recursive partials
+    /// are only listed once.
+    fn follow(&mut self, name: &str) -> Result<()> {
+        let Some(source) = self.partials.and_then(|partials| partials.get(name)) else {
+            return Ok(());
+        };
+        if self.chain.iter().any(|partial| partial == name) {
+            return Ok(());
+        }
+        let template = Template::compile_with_options(source, self.options.clone())?;
+        self.chain.push(name.into());
+        self.visit(template.root(), &LineIndex::new(source))?;
+        self.chain.pop();
+        Ok(())
+    }
+
+    fn push(&mut self, kind: ReferenceKind, name: String, lines: &LineIndex, offset: usize) {
+        self.references.push(Reference {
+            kind,
+            name,
+            path: self.path.clone(),
+            partial: self.chain.last().cloned(),
+            position: lines.locate(offset),
+        });
+    }
+}
@@ -0,0 +1,849 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+use std::slice;
+use std::time::Instant;
+
+use typed_arena::Arena;
+
+use crate::ast::{Argument, ArgumentValue, Filter, Name, Node, Root, Section, Variable, Variant};
+use crate::context::Context;
+use crate::error::{Error, Limit, Result};
+use crate::extensions::{Output, Write};
+use crate::helpers::{Block, Body, Resolved};
+use crate::options::{Backend, CompileOptions, RenderOptions, Truthiness};
+use crate::pragma::Settings;
+use crate::program::{Instruction, Program};
+use crate::template::{Partials, Template};
+
+/// The stack of contexts names are looked up in. A single stack is shared by
+/// the whole render: sections push the context they enter and pop it when
+/// done, so descending never copies the contexts already on the stack.
+pub struct ContextResolver<'c> {
+    stack: Vec<&'c Context>,
+    /// The stack a helper rendered its body from, searched after this one.
+    /// Helpers may push contexts that live shorter than the render, so they
+    /// start a stack of their own.
+    parent: Option<&'c ContextResolver<'c>>,
+    /// Whether dotted names may index into lists and take the length of
+    /// lists and strings.
+    indexing: bool,
+}
+
+impl<'c> ContextResolver<'c> {
+    pub fn new(base: &'c Context) -> Self {
+        Self {
+            stack: vec![base],
+            parent: None,
+            indexing: false,
+        }
+    }
+
+    pub(crate) fn indexing(mut self, enabled: bool) -> Self {
+        self.indexing = enabled;
+        self
+    }
+
+    fn child(parent: &'c ContextResolver<'c>, context: &'c Context) -> Self {
+        Self {
+            stack: vec![context],
+            parent: Some(parent),
+            indexing: parent.indexing,
+        }
+    }
+
+    fn push(&mut self, context: &'c Context) {
+        self.stack.push(context);
+    }
+
+    fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Looks up a name as a variable tag would, such as `user.name` or `.`,
+    /// returning `None` if it is not found or malformed.
+    pub fn get(&self, name: &str) -> Option<Cow<'c, Context>> {
+        let name = Name::parse(name)?;
+        self.check(&name).ok()?;
+        self.find(&name)
+    }
+
+    /// Looks up a name. The value is borrowed from the context unless the
+    /// lookup computes it, as it does for the length of a list.
+    fn find(&self, name: &Name) -> Option<Cow<'c, Context>> {
+        self.find_from(name, 0)
+    }
+
+    /// Looks up a name as if the `skip` innermost contexts were not there.
+    fn find_from(&self, name: &Name, skip: usize) -> Option<Cow<'c, Context>> {
+        match name {
+            Name::Implicit => self.context(skip).map(Cow::Borrowed),
+            Name::Key(key) => self.lookup(key, skip).map(Cow::Borrowed),
+            Name::Parent(levels, name) => self.find_from(name, skip + levels),
+            Name::Root(name) => self.find_from(name, self.depth().saturating_sub(1)),
+            Name::Path(keys) => {
+                let first = Cow::Borrowed(self.lookup(keys[0], skip)?);
+                self.descend_all(first, &keys[1..])
+            }
+            Name::Anchored(name) => {
+                let context = self.context(skip)?;
+                match &**name {
+                    Name::Key(key) => self.descend(context, key),
+                    Name::Path(keys) => {
+                        let first = self.descend(context, keys[0])?;
+                        self.descend_all(first, &keys[1..])
+                    }
+                    _ => unreachable!("only keys are anchored"),
+                }
+            }
+        }
+    }
+
+    /// Looks up each key in turn within the value found so far.
+    fn descend_all(&self, mut out: Cow<'c, Context>, keys: &[&str]) -> Option<Cow<'c, Context>> {
+        for key in keys {
+            out = match out {
+                Cow::Borrowed(context) => self.descend(context, key)?,
+                Cow::Owned(_) => return None,
+            };
+        }
+        Some(out)
+    }
+
+    /// Looks up a key within a value: a field of a map or, with indexing, an
+    /// item of a list, counting from the end if the index is negative, or
+    /// the length of a list or string.
+    fn descend(&self, context: &'c Context, key: &str) -> Option<Cow<'c, Context>> {
+        let length = |len: usize| Some(Cow::Owned(Context::Integer(len as i64)));
+        match context {
+            Context::Map(_) => context.get(key).map(Cow::Borrowed),
+            Context::List(items) if self.indexing && key == "length" => length(items.len()),
+            Context::List(items) if self.indexing => {
+                let index: i64 = key.parse().ok()?;
+                let index = match index < 0 {
+                    true => index + items.len() as i64,
+                    false => index,
+                };
+                items.get(usize::try_from(index).ok()?).map(Cow::Borrowed)
+            }
+            Context::String(text) if self.indexing && key == "length" => {
+                length(text.chars().count())
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds a key in the innermost context that has it, skipping the `skip`
+    /// innermost contexts.
+    fn lookup(&self, key: &str, skip: usize) -> Option<&'c Context> {
+        let contexts = self.stack.iter().rev().skip(skip);
+        match contexts.copied().find_map(|context| context.get(key)) {
+            Some(context) => Some(context),
+            None => self
+                .parent?
+                .lookup(key, skip.saturating_sub(self.stack.len())),
+        }
+    }
+
+    /// Returns the context `skip` contexts out from the innermost one.
+    fn context(&self, skip: usize) -> Option<&'c Context> {
+        match self.stack.len().checked_sub(skip + 1) {
+            Some(index) => Some(self.stack[index]),
+            None => self.parent?.context(skip - self.stack.len()),
+        }
+    }
+
+    /// Returns the number of contexts names are looked up in, down to the
+    /// one passed to the render.
+    fn depth(&self) -> usize {
+        self.stack.len() + self.parent.map_or(0, ContextResolver::depth)
+    }
+
+    /// Fails if a name skips as many contexts as there are or more, walking
+    /// past the context passed to the render.
+    fn check(&self, name: &Name) -> Result<()> {
+        match name {
+            Name::Parent(levels, _) if *levels >= self.depth() => {
+                Err(Error::Scope(name.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Walks a template tree, or runs the program it was lowered into, and writes
+/// the rendered output, keeping track of how deeply sections and partials are
+/// nested and which resource limits apply.
+pub struct Renderer<'r> {
+    partials: &'r Partials,
+    /// Holds the indented sources of partials, which the compiled partials
+    /// borrow from.
+    sources: &'r Arena<String>,
+    /// The partials compiled so far, by name and indentation.
+    compiled: HashMap<(String, String), Rc<Template<'r>>>,
+    compile_options: &'r CompileOptions,
+    options: &'r RenderOptions,
+    /// The settings of the template or partial being rendered.
+    settings: Settings,
+    out: String,
+    /// The sections and partials being rendered, outermost first. Sections
+    /// are written as their opening tag, as in `#items`.
+    chain: Vec<String>,
+    iterations: usize,
+    expansions: usize,
+    /// Work done since the deadline and cancellation flag were last checked,
+    /// in bytes written plus a fixed cost per write.
+    unchecked: usize,
+}
+
+/// The work counted for each write, so that many small writes are checked as
+/// often as a few large ones.
+const WRITE_COST: usize = 64;
+
+/// How much work is done between checks of the deadline and cancellation
+/// flag while writing.
+const CHECK_INTERVAL: usize = 64 * 1024;
+
+impl<'r> Renderer<'r> {
+    pub fn new(
+        partials: &'r Partials,
+        sources: &'r Arena<String>,
+        compile_options: &'r CompileOptions,
+        options: &'r RenderOptions,
+        settings: Settings,
+    ) -> Self {
+        Self {
+            partials,
+            sources,
+            compiled: HashMap::new(),
+            compile_options,
+            options,
+            settings,
+            out: String::new(),
+            chain: Vec::new(),
+            iterations: 0,
+            expansions: 0,
+            unchecked: 0,
+        }
+    }
+
+    pub fn render(mut self, node: &Node, mut resolver: ContextResolver) -> Result<String> {
+        self.render_node(node, &mut resolver)?;
+        Ok(self.out)
+    }
+
+    pub fn execute(mut self, program: &Program, mut resolver: ContextResolver) -> Result<String> {
+        self.run(program, 0..program.instructions().len(), &mut resolver)?;
+        Ok(self.out)
+    }
+
+    fn render_node(&mut self, node: &Node, resolver: &mut ContextResolver) -> Result<()> {
+        match node {
+            Node::Root(root) => self.render_root(root, resolver),
+            Node::Section(section) => self.render_section(section, resolver),
+            Node::Variable(variable) => self.render_variable(variable, resolver),
+            Node::Partial(partial) => self.render_partial(
+                partial.name,
+                partial.dynamic.as_ref(),
+                &partial.indent,
+                resolver,
+            ),
+            Node::Text(text) => self.render_text(text.text),
+            Node::Extension(extension) => {
+                self.render_extension(extension.sigil, extension.node.as_ref(), resolver)
+            }
+        }
+    }
+
+    fn render_root(&mut self, root: &Root, resolver: &mut ContextResolver) -> Result<()> {
+        self.render_children(&root.children, resolver)
+    }
+
+    fn render_children(&mut self, children: &[Node], resolver: &mut ContextResolver) -> Result<()> {
+        for child in children {
+            self.render_node(child, resolver)?;
+        }
+        Ok(())
+    }
+
+    fn render_section(&mut self, section: &Section, resolver: &mut ContextResolver) -> Result<()> {
+        if !section.args.is_empty() {
+            let children = Children::Nodes(&section.children);
+            return self.render_helper(&section.name, &section.args, children, resolver);
+        }
+        self.enter(Self::frame(section.variant, &section.name))?;
+        let context = match self.find_section(resolver, &section.name)? {
+            Some(Cow::Owned(value)) => {
+                let children = Children::Nodes(&section.children);
+                self.render_computed(section.variant, &value, children, resolver)?;
+                self.leave();
+                return Ok(());
+            }
+            Some(Cow::Borrowed(context)) => Some(context),
+            None => None,
+        };
+        let truthiness = self.truthiness();
+        match (section.variant, context) {
+            (Variant::Direct, Some(Context::List(list))) if !list.is_empty() => {
+                for context in list {
+                    self.iterate()?;
+                    resolver.push(context);
+                    self.render_children(&section.children, resolver)?;
+                    resolver.pop();
+                }
+            }
+            (Variant::Direct, Some(context)) if truthiness.is_truthy(context) => {
+                self.iterate()?;
+                resolver.push(context);
+                self.render_children(&section.children, resolver)?;
+                resolver.pop();
+            }
+            (Variant::Inverse, Some(context)) if !truthiness.is_truthy(context) => {
+                self.iterate()?;
+                self.render_children(&section.children, resolver)?;
+            }
+            (Variant::Inverse, None) => {
+                self.iterate()?;
+                self.render_children(&section.children, resolver)?;
+            }
+            _ => (),
+        };
+        self.leave();
+        Ok(())
+    }
+
+    fn render_variable(&mut self, variable: &Variable, resolver: &ContextResolver) -> Result<()> {
+        let context = self.find(resolver, &variable.name)?;
+        let context = context.as_deref();
+        if !variable.filters.is_empty() {
+            let filtered = self.filter(context, &variable.filters)?;
+            return self.write_value(filtered.as_ref(), variable.escaped);
+        }
+        self.write_value(context, variable.escaped)
+    }
+
+    /// Passes a value through filters in order. Escaping happens afterwards,
+    /// so filters see the value as it is in the context.
+    fn filter(&self, context: Option<&Context>, filters: &[Filter]) -> Result<Option<Context>> {
+        let mut value = None;
+        for (i, filter) in filters.iter().enumerate() {
+            let Some(apply) = self.options.filters.get(filter.name) else {
+                return Err(Error::Filter {
+                    name: filter.name.into(),
+                    message: "no filter has this name".into(),
+                });
+            };
+            let input = if i == 0 { context } else { value.as_ref() };
+            value = apply(input, &filter.args).map_err(|message| Error::Filter {
+                name: filter.name.into(),
+                message,
+            })?;
+        }
+        Ok(value)
+    }
+
+    /// Calls the helper a section names with its arguments, giving it the
+    /// children of the section to render.
+    fn render_helper(
+        &mut self,
+        name: &Name,
+        args: &[Argument],
+        children: Children,
+        resolver: &mut ContextResolver,
+    ) -> Result<()> {
+        let name = name.to_string();
+        let options = self.options;
+        let Some(helper) = options.helpers.get(&name) else {
+            return Err(Error::Helper {
+                name,
+                message: "no helper has this name".into(),
+            });
+        };
+        self.enter(format!("#{name}"))?;
+        let args: Vec<Resolved> = args
+            .iter()
+            .map(|arg| {
+                let value = match &arg.value {
+                    ArgumentValue::Name(name) => {
+                        resolver.check(name)?;
+                        resolver.find(name)
+                    }
+                    ArgumentValue::String(text) => {
+                        Some(Cow::Owned(Context::String(text.to_string())))
+                    }
+                    ArgumentValue::Number(number) => Some(Cow::Owned(match number.parse() {
+                        Ok(number) => Context::Integer(number),
+                        Err(_) => Context::Float(number.parse().unwrap_or_default()),
+                    })),
+                };
+                Ok((arg.key, value))
+            })
+            .collect::<Result<_>>()?;
+        let mut body = HelperBody {
+            renderer: self,
+            resolver,
+            children,
+        };
+        helper(&mut Block::new(&name, &args, &mut body))?;
+        self.leave();
+        Ok(())
+    }
+
+    fn render_body(&mut self, children: &Children, resolver: &mut ContextResolver) -> Result<()> {
+        match children {
+            Children::Nodes(nodes) => self.render_children(nodes, resolver),
+            Children::Instructions(program, range) => self.run(program, range.clone(), resolver),
+        }
+    }
+
+    /// Renders a partial. A dynamic partial renders the partial its name
+    /// looks up, or nothing when the value is not a string.
+    fn render_partial(
+        &mut self,
+        name: &str,
+        dynamic: Option<&Name>,
+        indent: &str,
+        resolver: &mut ContextResolver,
+    ) -> Result<()> {
+        let name = match dynamic {
+            None => Cow::Borrowed(name),
+            Some(dynamic) => match self.find(resolver, dynamic)?.as_deref() {
+                Some(Context::String(name)) => Cow::Owned(name.clone()),
+                _ => return Ok(()),
+            },
+        };
+        let name = &*name;
+        let template = match self.compile_partial(name, indent) {
+            Ok(Some(template)) => template,
+            Ok(None) => return Ok(()),
+            Err(error @ (Error::Limit(_) | Error::Pragma { .. })) => return Err(error),
+            // A partial that does not parse renders nothing, as a missing
+            // partial does.
+            Err(_) => return Ok(()),
+        };
+        self.expansions += 1;
+        if self
+            .options
+            .max_partials
+            .is_some_and(|max| self.expansions > max)
+        {
+            return Err(Error::Limit(Limit::Partials));
+        }
+        self.enter(name.to_string())?;
+        let settings = std::mem::replace(&mut self.settings, template.settings());
+        match self.compile_options.backend {
+            Backend::Tree => self.render_node(template.root(), resolver)?,
+            Backend::Program => {
+                let program = template.program();
+                self.run(program, 0..program.instructions().len(), resolver)?
+            }
+        }
+        self.settings = settings;
+        self.leave();
+        Ok(())
+    }
+
+    /// Compiles a partial with the given indentation, or returns the copy
+    /// compiled earlier in the render. Returns `None` for a missing partial.
+    fn compile_partial(&mut self, name: &str, indent: &str) -> Result<Option<Rc<Template<'r>>>> {
+        let key = (name.to_string(), indent.to_string());
+        if let Some(template) = self.compiled.get(&key) {
+            return Ok(Some(template.clone()));
+        }
+        let Some(source) = self.partials.get(name) else {
+            return Ok(None);
+        };
+        let source = match indent {
+            "" => source.as_str(),
+            _ => self.sources.alloc(Self::indent(source, indent)).as_str(),
+        };
+        let template = Rc::new(Template::compile_with_options(
+            source,
+            self.compile_options.clone(),
+        )?);
+        self.compiled.insert(key, template.clone());
+        Ok(Some(template))
+    }
+
+    fn render_extension(
+        &mut self,
+        sigil: char,
+        node: &(dyn Any + Send + Sync),
+        resolver: &ContextResolver,
+    ) -> Result<()> {
+        let options = self.compile_options;
+        let Some(extension) = options.extensions.get(sigil) else {
+            return Err(Error::Extension {
+                sigil,
+                message: "no extension has this sigil".into(),
+            });
+        };
+        (extension.render)(node, resolver, &mut Output::new(self))
+    }
+
+    /// Renders a section whose value the lookup computed, such as the length
+    /// of a list. The value lives shorter than the contexts on the stack, so
+    /// the body starts a stack of its own, as the bodies of helpers do.
+    fn render_computed(
+        &mut self,
+        variant: Variant,
+        value: &Context,
+        children: Children,
+        resolver: &mut ContextResolver,
+    ) -> Result<()> {
+        let truthy = self.truthiness().is_truthy(value);
+        match (variant, truthy) {
+            (Variant::Direct, true) => {
+                self.iterate()?;
+                let mut child = ContextResolver::child(resolver, value);
+                self.render_body(&children, &mut child)
+            }
+            (Variant::Inverse, false) => {
+                self.iterate()?;
+                self.render_body(&children, resolver)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn render_text(&mut self, text: &str) -> Result<()> {
+        self.write(text)
+    }
+
+    /// Runs a range of the instructions of a program, which is the whole
+    /// program or the body of a helper section. Each section that renders
+    /// keeps a frame with the items it iterates over until its body ends for
+    /// the last time.
+    fn run<'c>(
+        &mut self,
+        program: &Program,
+        range: Range<usize>,
+        resolver: &mut ContextResolver<'c>,
+    ) -> Result<()> {
+        let instructions = program.instructions();
+        let mut frames: Vec<Frame<'c>> = Vec::new();
+        let mut pc = range.start;
+        while pc < range.end {
+            let instruction = &instructions[pc];
+            pc += 1;
+            match instruction {
+                Instruction::Text(text) => self.write(text)?,
+                Instruction::Variable {
+                    name,
+                    escaped,
+                    filters,
+                } if !filters.is_empty() => {
+                    let context = self.find(resolver, name)?;
+                    let filtered = self.filter(context.as_deref(), filters)?;
+                    self.write_value(filtered.as_ref(), *escaped)?
+                }
+                Instruction::Variable { name, escaped, .. } => {
+                    self.write_value(self.find(resolver, name)?.as_deref(), *escaped)?
+                }
+                Instruction::Section { name, variant, end } => {
+                    self.enter(Self::frame(*variant, name))?;
+                    let context = match self.find_section(resolver, name)? {
+                        Some(Cow::Owned(value)) => {
+                            // The body ends just before the `End` instruction.
+                            let children = Children::Instructions(program, pc..*end - 1);
+                            self.render_computed(*variant, &value, children, resolver)?;
+                            self.leave();
+                            pc = *end;
+                            continue;
+                        }
+                        Some(Cow::Borrowed(context)) => Some(context),
+                        None => None,
+                    };
+                    let truthiness = self.truthiness();
+                    let items: Option<&[Context]> = match (variant, context) {
+                        (Variant::Direct, Some(Context::List(list))) if !list.is_empty() => {
+                            Some(list)
+                        }
+                        (Variant::Direct, Some(context)) if truthiness.is_truthy(context) => {
+                            Some(slice::from_ref(context))
+                        }
+                        (Variant::Inverse, Some(context)) if !truthiness.is_truthy(context) => {
+                            Some(&[])
+                        }
+                        (Variant::Inverse, None) => Some(&[]),
+                        _ => None,
+                    };
+                    let Some(items) = items else {
+                        self.leave();
+                        pc = *end;
+                        continue;
+                    };
+                    self.iterate()?;
+                    if let Some(first) = items.first() {
+                        resolver.push(first);
+                    }
+                    frames.push(Frame { items, index: 0 });
+                }
+                Instruction::End { start } => {
+                    let frame = frames.last_mut().expect("sections are balanced");
+                    if !frame.items.is_empty() {
+                        resolver.pop();
+                    }
+                    if frame.index + 1 < frame.items.len() {
+                        frame.index += 1;
+                        self.iterate()?;
+                        resolver.push(&frame.items[frame.index]);
+                        pc = start + 1;
+                    } else {
+                        frames.pop();
+                        self.leave();
+                    }
+                }
+                Instruction::Helper { name, args, end } => {
+                    let children = Children::Instructions(program, pc..*end);
+                    self.render_helper(name, args, children, resolver)?;
+                    pc = *end;
+                }
+                Instruction::Partial {
+                    name,
+                    dynamic,
+                    indent,
+                } => self.render_partial(name, dynamic.as_ref(), indent, resolver)?,
+                Instruction::Extension { sigil, node } => {
+                    self.render_extension(*sigil, node.as_ref(), resolver)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up the name of a variable, failing if it is not found and the
+    /// template is strict.
+    fn find<'c>(
+        &self,
+        resolver: &ContextResolver<'c>,
+        name: &Name,
+    ) -> Result<Option<Cow<'c, Context>>> {
+        resolver.check(name)?;
+        let context = resolver.find(name);
+        match context {
+            None if self.settings.strict => Err(Error::Missing(name.to_string())),
+            _ => Ok(context),
+        }
+    }
+
+    /// Looks up the name of a section, failing if it is not found and the
+    /// template is strict about sections as well.
+    fn find_section<'c>(
+        &self,
+        resolver: &ContextResolver<'c>,
+        name: &Name,
+    ) -> Result<Option<Cow<'c, Context>>> {
+        resolver.check(name)?;
+        let context = resolver.find(name);
+        match context {
+            None if self.settings.strict_sections => Err(Error::Missing(name.to_string())),
+            _ => Ok(context),
+        }
+    }
+
+    /// The truthiness rules of the template or partial being rendered, which
+    /// a `TRUTHINESS` pragma sets in place of those of the render options.
+    fn truthiness(&self) -> &Truthiness {
+        match &self.settings.truthiness {
+            Some(truthiness) => truthiness,
+            None => &self.options.truthiness,
+        }
+    }
+
+    /// Writes the value of a lookup, escaping strings as they are copied
+    /// rather than building an escaped copy first.
+    fn write_value(&mut self, context: Option<&Context>, escaped: bool) -> Result<()> {
+        match (context, escaped) {
+            // Maps and lists have no text of their own, as in the typed path.
+            (None | Some(Context::Map(_) | Context::List(_)), _) => Ok(()),
+            (Some(Context::String(text)), true) => self.write_escaped(text),
+            (Some(Context::String(text)), false) => self.write(text),
+            (Some(context), true) => self.write_escaped(&context.to_text()),
+            (Some(context), false) => self.write(&context.to_text()),
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<()> {
+        self.reserve(text.len())?;
+        self.out.push_str(text);
+        Ok(())
+    }
+
+    fn write_escaped(&mut self, text: &str) -> Result<()> {
+        let len = text
+            .bytes()
+            .map(|byte| escape_byte(byte).map_or(1, str::len))
+            .sum();
+        self.reserve(len)?;
+        let mut start = 0;
+        for (i, byte) in text.bytes().enumerate() {
+            if let Some(escape) = escape_byte(byte) {
+                self.out.push_str(&text[start..i]);
+                self.out.push_str(escape);
+                start = i + 1;
+            }
+        }
+        self.out.push_str(&text[start..]);
+        Ok(())
+    }
+
+    /// Checks that `len` more bytes of output stay within the output limit,
+    /// and every so often that the render has not run out of time.
+    fn reserve(&mut self, len: usize) -> Result<()> {
+        if self
+            .options
+            .max_output
+            .is_some_and(|max| self.out.len() + len > max)
+        {
+            return Err(Error::Limit(Limit::Output));
+        }
+        self.unchecked += len + WRITE_COST;
+        if self.unchecked >= CHECK_INTERVAL {
+            self.unchecked = 0;
+            self.check_time()?;
+        }
+        Ok(())
+    }
+
+    /// Descends into a section or partial, failing once the maximum depth is
+    /// exceeded.
+    fn enter(&mut self, frame: String) -> Result<()> {
+        self.chain.push(frame);
+        if self.chain.len() > self.options.max_depth {
+            return Err(Error::Depth(std::mem::take(&mut self.chain)));
+        }
+        self.check_time()
+    }
+
+    fn leave(&mut self) {
+        self.chain.pop();
+    }
+
+    /// Names a section in the chain the way its opening tag is written.
+    fn frame(variant: Variant, name: &Name) -> String {
+        match variant {
+            Variant::Direct => format!("#{name}"),
+            Variant::Inverse => format!("^{name}"),
+        }
+    }
+
+    /// Counts one rendering of a section body.
+    fn iterate(&mut self) -> Result<()> {
+        self.iterations += 1;
+        if self
+            .options
+            .max_iterations
+            .is_some_and(|max| self.iterations > max)
+        {
+            return Err(Error::Limit(Limit::Iterations));
+        }
+        self.check_time()
+    }
+
+    fn check_time(&self) -> Result<()> {
+        if self.options.is_cancelled() {
+            return Err(Error::Limit(Limit::Cancelled));
+        }
+        if self
+            .options
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Error::Limit(Limit::Deadline));
+        }
+        Ok(())
+    }
+
+    fn indent(partial: &str, indent: &str) -> String {
+        Self::lines(partial)
+            .iter()
+            .map(|line| indent.to_owned() + line)
+            .collect::<Vec<String>>()
+            .join("")
+    }
+
+    fn lines(input: &str) -> Vec<&str> {
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        let mut line_len = 0;
+        for c in input.chars() {
+            line_len += 1;
+            if c == '\n' {
+                lines.push(&input[line_start..line_start + line_len]);
+                line_start += line_len;
+                line_len = 0;
+            }
+        }
+        if line_len > 0 {
+            lines.push(&input[line_start..line_start + line_len]);
+        }
+        lines
+    }
+}
+
+/// The body of a helper section: nodes of the tree, or a range of the
+/// instructions of a program.
+enum Children<'a> {
+    Nodes(&'a [Node<'a>]),
+    Instructions(&'a Program<'a>, Range<usize>),
+}
+
+impl Write for Renderer<'_> {
+    fn write(&mut self, text: &str) -> Result<()> {
+        Renderer::write(self, text)
+    }
+
+    fn write_escaped(&mut self, text: &str) -> Result<()> {
+        Renderer::write_escaped(self, text)
+    }
+}
+
+/// Renders the body of a helper section for the `Block` given to the helper.
+struct HelperBody<'a, 'r, 'c> {
+    renderer: &'a mut Renderer<'r>,
+    resolver: &'a mut ContextResolver<'c>,
+    children: Children<'a>,
+}
+
+impl Body for HelperBody<'_, '_, '_> {
+    fn render(&mut self, context: Option<&Context>) -> Result<()> {
+        self.renderer.iterate()?;
+        match context {
+            Some(context) => {
+                let mut child = ContextResolver::child(self.resolver, context);
+                self.renderer.render_body(&self.children, &mut child)
+            }
+            None => self.renderer.render_body(&self.children, self.resolver),
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<()> {
+        self.renderer.write(text)
+    }
+
+    fn is_truthy(&self, value: &Context) -> bool {
+        self.renderer.truthiness().is_truthy(value)
+    }
+}
+
+/// A section being rendered by a program: the items its body is rendered
+/// for, or none for an inverted section whose body is rendered once.
+struct Frame<'c> {
+    items: &'c [Context],
+    index: usize,
+}
+
+/// Returns the HTML entity an escaped value writes in place of a byte.
+pub(crate) fn escape_byte(byte: u8) -> Option<&'static str> {
+    match byte {
+        b'&' => Some("&amp;"),
+        b'>' => Some("&gt;"),
+        b'<' => Some("&lt;"),
+        b'"' => Some("&quot;"),
+        b'\'' => Some("&#39;"),
+        _ => None,
+    }
+}
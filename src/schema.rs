@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{ArgumentValue, LineIndex, Name, Node, Position, Variant};
+use crate::context::Context;
+use crate::error::Result;
+use crate::options::CompileOptions;
+use crate::template::{Partials, Template};
+
+/// Describes the shape of the data a template is rendered with.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Schema {
+    /// A string, number or boolean.
+    Scalar,
+    /// A map with the given fields.
+    Map(HashMap<String, Schema>),
+    /// A list whose elements all have the given shape.
+    List(Box<Schema>),
+    /// A value of unknown shape, which any lookup is allowed on.
+    Any,
+}
+
+impl Schema {
+    pub fn map<'a>(fields: impl IntoIterator<Item = (&'a str, Schema)>) -> Self {
+        Schema::Map(
+            fields
+                .into_iter()
+                .map(|(name, schema)| (name.into(), schema))
+                .collect(),
+        )
+    }
+
+    pub fn list(element: Schema) -> Self {
+        Schema::List(Box::new(element))
+    }
+
+    /// Derives a schema from a sample context. The fields of maps found in the
+    /// same list are merged, and `Null` values are taken to be of any shape.
+    pub fn from_context(context: &Context) -> Self {
+        match context {
+            Context::String(_) | Context::Integer(_) | Context::Float(_) | Context::Bool(_) => {
+                Schema::Scalar
+            }
+            Context::Null => Schema::Any,
+            Context::Map(map) => Schema::Map(
+                map.iter()
+                    .map(|(name, context)| (name.clone(), Schema::from_context(context)))
+                    .collect(),
+            ),
+            Context::List(list) => Schema::list(
+                list.iter()
+                    .map(Schema::from_context)
+                    .reduce(Schema::merge)
+                    .unwrap_or(Schema::Any),
+            ),
+        }
+    }
+
+    /// Derives a schema from a JSON Schema document that has been loaded into a
+    /// context. Objects are read from `properties`, arrays from `items`, and
+    /// anything without a recognized `type` is taken to be of any shape.
+    pub fn from_json_schema(document: &Context) -> Self {
+        let kind = match document.get("type") {
+            Some(Context::String(kind)) => kind.as_str(),
+            _ => "",
+        };
+        match kind {
+            "string" | "number" | "integer" | "boolean" => Schema::Scalar,
+            "object" => match document.get("properties") {
+                Some(Context::Map(properties)) => Schema::Map(
+                    properties
+                        .iter()
+                        .map(|(name, property)| (name.clone(), Schema::from_json_schema(property)))
+                        .collect(),
+                ),
+                _ => Schema::Any,
+            },
+            "array" => Schema::list(
+                document
+                    .get("items")
+                    .map_or(Schema::Any, Schema::from_json_schema),
+            ),
+            _ => Schema::Any,
+        }
+    }
+
+    fn merge(self, other: Schema) -> Schema {
+        match (self, other) {
+            (Schema::Any, other) | (other, Schema::Any) => other,
+            (Schema::Scalar, Schema::Scalar) => Schema::Scalar,
+            (Schema::List(a), Schema::List(b)) => Schema::list(a.merge(*b)),
+            (Schema::Map(mut a), Schema::Map(b)) => {
+                for (name, schema) in b {
+                    let merged = match a.remove(&name) {
+                        Some(existing) => existing.merge(schema),
+                        None => schema,
+                    };
+                    a.insert(name, merged);
+                }
+                Schema::Map(a)
+            }
+            _ => Schema::Any,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Schema> {
+        match self {
+            Schema::Map(fields) => fields.get(name),
+            Schema::Any => Some(&Schema::Any),
+            _ => None,
+        }
+    }
+}
+
+/// A mistake found when checking a template against a schema.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub name: String,
+    /// The partial the issue appears in, or `None` for the template itself.
+    pub partial: Option<String>,
+    pub position: Position,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IssueKind {
+    /// The name is not provided by any enclosing scope.
+    Unknown,
+    /// A dotted name descends into a value that is not a map.
+    NotAMap,
+    /// A variable interpolates a map or a list.
+    NotAScalar,
+    /// A section is opened on a scalar as if it were a list, which renders
+    /// its body once at most.
+    NotAList,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, column } = self.position;
+        if let Some(partial) = &self.partial {
+            write!(f, "{partial}:")?;
+        }
+        write!(f, "{line}:{column}: ")?;
+        match self.kind {
+            IssueKind::Unknown => write!(f, "unknown name `{}`", self.name),
+            IssueKind::NotAMap => {
+                write!(f, "`{}` descends into a value that is not a map", self.name)
+            }
+            IssueKind::NotAScalar => write!(f, "`{}` interpolates a map or list", self.name),
+            IssueKind::NotAList => write!(f, "`{}` opens a section on a scalar", self.name),
+        }
+    }
+}
+
+/// Walks a template with a stack of schemas, mirroring how sections push
+/// contexts while rendering, and records the lookups that cannot succeed.
+pub(crate) struct Checker<'p> {
+    partials: Option<&'p Partials>,
+    options: &'p CompileOptions,
+    chain: Vec<String>,
+    issues: Vec<Issue>,
+}
+
+impl<'p> Checker<'p> {
+    pub fn new(partials: Option<&'p Partials>, options: &'p CompileOptions) -> Self {
+        Self {
+            partials,
+            options,
+            chain: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, template: &Template, schema: &Schema) -> Result<Vec<Issue>> {
+        self.visit(
+            template.root(),
+            &LineIndex::new(template.text()),
+            &mut vec![schema],
+        )?;
+        Ok(self.issues)
+    }
+
+    fn visit(&mut self, node: &Node, lines: &LineIndex, stack: &mut Vec<&Schema>) -> Result<()> {
+        match node {
+            Node::Root(root) => {
+                for child in &root.children {
+                    self.visit(child, lines, stack)?;
+                }
+            }
+            // The name of a helper section is not looked up. Its body is
+            // checked in the enclosing scope, which is what the built-in
+            // helpers render it into.
+            Node::Section(section) if !section.args.is_empty() => {
+                for arg in &section.args {
+                    if let ArgumentValue::Name(name) = &arg.value {
+                        self.find(stack, name, lines, section.open.start);
+                    }
+                }
+                for child in &section.children {
+                    self.visit(child, lines, stack)?;
+                }
+            }
+            Node::Section(section) => {
+                let offset = section.open.start;
+                let found = self.find(stack, &section.name, lines, offset);
+                if let (Variant::Direct, Some(Schema::Scalar)) = (section.variant, found) {
+                    self.push(IssueKind::NotAList, &section.name, lines, offset);
+                }
+                let pushed = match (section.variant, found) {
+                    (Variant::Direct, Some(Schema::List(element))) => Some(element.as_ref()),
+                    (Variant::Direct, Some(schema)) => Some(schema),
+                    _ => None,
+                };
+                if let Some(schema) = pushed {
+                    stack.push(schema);
+                }
+                for child in &section.children {
+                    self.visit(child, lines, stack)?;
+                }
+                if pushed.is_some() {
+                    stack.pop();
+                }
+            }
+            Node::Variable(variable) => {
+                let offset = variable.span.start;
+                let found = self.find(stack, &variable.name, lines, offset);
+                // Filters such as `json` accept maps and lists.
+                if matches!(found, Some(Schema::Map(_) | Schema::List(_)))
+                    && variable.filters.is_empty()
+                {
+                    self.push(IssueKind::NotAScalar, &variable.name, lines, offset);
+                }
+            }
+            // The partial a dynamic name includes is only known when rendering.
+            Node::Partial(partial) => match &partial.dynamic {
+                Some(name) => {
+                    self.find(stack, name, lines, partial.span.start);
+                }
+                None => self.follow(partial.name, stack)?,
+            },
+            Node::Text(_) | Node::Extension(_) => (),
+        }
+        Ok(())
+    }
+
+    /// Resolves a name the way `ContextResolver::find` does, recording an issue
+    /// when the lookup cannot succeed.
+    fn find<'s>(
+        &mut self,
+        stack: &[&'s Schema],
+        name: &Name,
+        lines: &LineIndex,
+        offset: usize,
+    ) -> Option<&'s Schema> {
+        let (stack, scoped) = match name {
+            Name::Parent(levels, _) if *levels >= stack.len() => {
+                self.push(IssueKind::Unknown, name, lines, offset);
+                return None;
+            }
+            Name::Parent(levels, scoped) => (&stack[..stack.len() - levels], &**scoped),
+            Name::Root(scoped) => (&stack[..1], &**scoped),
+            Name::Anchored(scoped) => (&stack[stack.len() - 1..], &**scoped),
+            _ => (stack, name),
+        };
+        let (first, rest) = match scoped {
+            Name::Implicit => return stack.last().copied(),
+            Name::Key(key) => (*key, &[][..]),
+            Name::Path(keys) => (keys[0], &keys[1..]),
+            Name::Parent(..) | Name::Root(_) | Name::Anchored(_) => {
+                unreachable!("scopes do not nest")
+            }
+        };
+
+        let Some(mut out) = stack.iter().rev().find_map(|schema| schema.get(first)) else {
+            self.push(IssueKind::Unknown, name, lines, offset);
+            return None;
+        };
+
+        let indexing = self.options.list_indexing;
+        for key in rest {
+            out = match (out, out.get(key)) {
+                (_, Some(schema)) => schema,
+                (Schema::List(_) | Schema::Scalar, None) if indexing && *key == "length" => {
+                    &Schema::Scalar
+                }
+                (Schema::List(element), None) if indexing && key.parse::<i64>().is_ok() => element,
+                (Schema::Map(_), None) => {
+                    self.push(IssueKind::Unknown, name, lines, offset);
+                    return None;
+                }
+                _ => {
+                    self.push(IssueKind::NotAMap, name, lines, offset);
+                    return None;
+                }
+            };
+        }
+
+        Some(out)
+    }
+
+    fn follow(&mut self, name: &str, stack: &mut Vec<&Schema>) -> Result<()> {
+        let Some(source) = self.partials.and_then(|partials| partials.get(name)) else {
+            return Ok(());
+        };
+        if self.chain.iter().any(|partial| partial == name) {
+            return Ok(());
+        }
+        let template = Template::compile_with_options(source, self.options.clone())?;
+        self.chain.push(name.into());
+        self.visit(template.root(), &LineIndex::new(source), stack)?;
+        self.chain.pop();
+        Ok(())
+    }
+
+    fn push(&mut self, kind: IssueKind, name: &Name, lines: &LineIndex, offset: usize) {
+        self.issues.push(Issue {
+            kind,
+            name: name.to_string(),
+            partial: self.chain.last().cloned(),
+            position: lines.locate(offset),
+        });
+    }
+}
@@ -1,189 +1,143 @@
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
-use crate::ast::{Node, Partial, Root, Section, Variable, Variant};
+use typed_arena::Arena;
+
+use crate::ast::Node;
 use crate::context::Context;
 use crate::error::Result;
+use crate::options::{Backend, CompileOptions, RenderOptions};
 use crate::parser::Parser;
+use crate::pragma::Settings;
+use crate::program::Program;
+use crate::references::{Collector, Reference};
+use crate::render::{ContextResolver, Renderer};
+use crate::schema::{Checker, Issue, Schema};
 
 pub type Partials = HashMap<String, String>;
 
-#[derive(Clone)]
-pub struct ContextResolver<'a> {
-    stack: Vec<&'a Context>,
+pub struct Template<'t> {
+    text: &'t str,
+    root: Node<'t>,
+    options: CompileOptions,
+    /// The lowered syntax tree, built on first use by the program backend.
+    program: OnceLock<Program<'t>>,
 }
 
-impl<'a> ContextResolver<'a> {
-    fn new(base: &'a Context) -> Self {
-        Self { stack: vec![base] }
+impl<'t> Template<'t> {
+    pub fn compile(text: &'t str) -> Result<Self> {
+        Self::compile_with_options(text, CompileOptions::default())
     }
 
-    fn push(&self, context: &'a Context) -> Self {
-        let mut clone = self.clone();
-        clone.stack.push(context);
-        clone
+    /// Compiles a template with the given options. Partials included by the
+    /// template are compiled with the same options when it is rendered.
+    pub fn compile_with_options(text: &'t str, options: CompileOptions) -> Result<Self> {
+        let root = Parser::parse(text, &options)?;
+        let template = Self {
+            text,
+            root,
+            options,
+            program: OnceLock::new(),
+        };
+        if template.options.backend == Backend::Program {
+            template.program();
+        }
+        Ok(template)
     }
 
-    fn find(&self, name: &str) -> Option<&Context> {
-        if name == "." {
-            return self.stack.last().copied();
+    /// Builds a template from a syntax tree that was parsed ahead of time, for
+    /// example by the `stache!` macro of the `stache-macros` crate. The spans
+    /// in the tree must refer to `text`.
+    pub fn from_ast(text: &'t str, root: Node<'t>) -> Self {
+        Self {
+            text,
+            root,
+            options: CompileOptions::default(),
+            program: OnceLock::new(),
         }
+    }
 
-        let segments: Vec<&str> = name.split(".").collect();
+    pub fn render(&self, context: Context) -> Result<String> {
+        self.render_with_options(context, Partials::new(), RenderOptions::default())
+    }
 
-        let mut out = None;
-        for context in self.stack.iter().rev() {
-            if let Some(context) = context.get(segments[0]) {
-                out = Some(context);
-                break;
-            }
-        }
+    pub fn render_with_partials(&self, context: Context, partials: Partials) -> Result<String> {
+        self.render_with_options(context, partials, RenderOptions::default())
+    }
 
-        for segment in &segments[1..] {
-            match out {
-                None => break,
-                Some(context @ Context::Map(_)) => out = context.get(&segment),
-                _ => out = None,
-            }
+    pub fn render_with_options(
+        &self,
+        context: Context,
+        partials: Partials,
+        options: RenderOptions,
+    ) -> Result<String> {
+        let sources = Arena::new();
+        let renderer = Renderer::new(
+            &partials,
+            &sources,
+            &self.options,
+            &options,
+            self.settings(),
+        );
+        let resolver = ContextResolver::new(&context).indexing(self.options.list_indexing);
+        match self.options.backend {
+            Backend::Tree => renderer.render(&self.root, resolver),
+            Backend::Program => renderer.execute(self.program(), resolver),
         }
-
-        out
     }
-}
-
-pub struct Template<'t> {
-    root: Node<'t>,
-}
 
-impl<'t> Template<'t> {
-    pub fn compile(text: &'t str) -> Result<Self> {
-        let root = Parser::parse(text)?;
-        Ok(Self { root })
+    /// Lists the variables, sections and partials the template refers to, in
+    /// the order they appear in the source.
+    pub fn references(&self) -> Result<Vec<Reference>> {
+        Collector::new(None, &self.options).collect(self)
     }
 
-    pub fn render(&self, context: Context) -> String {
-        Self::render_node(&self.root, ContextResolver::new(&context), &Partials::new())
+    /// Lists the references of the template along with those of the partials
+    /// it includes, following partials through the given map.
+    pub fn references_with_partials(&self, partials: &Partials) -> Result<Vec<Reference>> {
+        Collector::new(Some(partials), &self.options).collect(self)
     }
 
-    pub fn render_with_partials(&self, context: Context, partials: Partials) -> String {
-        Self::render_node(&self.root, ContextResolver::new(&context), &partials)
+    /// Checks that every name the template looks up is provided by the schema,
+    /// following the same scoping rules as rendering.
+    pub fn check(&self, schema: &Schema) -> Result<Vec<Issue>> {
+        Checker::new(None, &self.options).check(self, schema)
     }
 
-    fn render_node(node: &Node, resolver: ContextResolver, partials: &Partials) -> String {
-        match node {
-            Node::Root(root) => Self::render_root(root, resolver, partials),
-            Node::Section(section) => Self::render_section(section, resolver, partials),
-            Node::Variable(variable) => Self::render_variable(variable, resolver),
-            Node::Partial(partial) => Self::render_partial(partial, resolver, partials),
-            Node::Text(text) => Self::render_text(text),
-        }
+    /// Checks the template and the partials it includes against the schema.
+    pub fn check_with_partials(&self, schema: &Schema, partials: &Partials) -> Result<Vec<Issue>> {
+        Checker::new(Some(partials), &self.options).check(self, schema)
     }
 
-    fn render_root(root: &Root, resolver: ContextResolver, partials: &Partials) -> String {
-        root.children
-            .iter()
-            .map(|child| Self::render_node(&child, resolver.clone(), partials))
-            .collect::<Vec<String>>()
-            .join("")
-    }
-
-    fn render_section(section: &Section, resolver: ContextResolver, partials: &Partials) -> String {
-        let context = resolver.find(section.name);
-        match (section.variant, context) {
-            (Variant::Direct, Some(Context::List(list))) if !list.is_empty() => {
-                let mut out = String::new();
-                for context in list {
-                    for child in &section.children {
-                        out.push_str(&Self::render_node(&child, resolver.push(context), partials));
-                    }
-                }
-                out
-            }
-            (Variant::Direct, Some(context)) if context.is_truthy() => section
-                .children
-                .iter()
-                .map(|child| Self::render_node(&child, resolver.push(context), partials))
-                .collect::<Vec<String>>()
-                .join(""),
-            (Variant::Inverse, Some(context)) if !context.is_truthy() => section
-                .children
-                .iter()
-                .map(|child| Self::render_node(&child, resolver.clone(), partials))
-                .collect::<Vec<String>>()
-                .join(""),
-            (Variant::Inverse, None) => section
-                .children
-                .iter()
-                .map(|child| Self::render_node(&child, resolver.clone(), partials))
-                .collect::<Vec<String>>()
-                .join(""),
-            _ => String::new(),
-        }
+    /// Returns the syntax tree of the template, whose root is a `Node::Root`.
+    pub fn ast(&self) -> &Node<'t> {
+        &self.root
     }
 
-    fn render_variable(variable: &Variable, resolver: ContextResolver) -> String {
-        let raw = resolver
-            .find(variable.name)
-            .map_or(String::new(), |context| context.to_string());
-        if variable.escaped {
-            Self::escape(&raw)
-        } else {
-            raw
-        }
+    /// Returns the syntax tree of the template for editing. Changes are
+    /// reflected in later renders.
+    pub fn ast_mut(&mut self) -> &mut Node<'t> {
+        self.program.take();
+        &mut self.root
     }
 
-    fn render_partial(partial: &Partial, resolver: ContextResolver, partials: &Partials) -> String {
-        let Partial { name, indent } = partial;
-        match partials.get(*name) {
-            None => String::new(),
-            Some(partial) => match Template::compile(&Self::indent(partial, &indent)) {
-                Err(_) => String::new(),
-                Ok(template) => Self::render_node(&template.root, resolver, partials),
-            },
-        }
+    pub(crate) fn text(&self) -> &'t str {
+        self.text
     }
 
-    fn render_text(text: &str) -> String {
-        text.into()
+    pub(crate) fn root(&self) -> &Node<'t> {
+        &self.root
     }
 
-    fn escape(input: &str) -> String {
-        const ESCAPES: [(&str, &str); 5] = [
-            ("&", "&amp;"),
-            (">", "&gt;"),
-            ("<", "&lt;"),
-            ("\"", "&quot;"),
-            ("'", "&#39;"),
-        ];
-        let mut out = String::from(input);
-        for (from, to) in ESCAPES {
-            out = out.replace(from, to);
-        }
-        out
-    }
-
-    fn indent(partial: &str, indent: &str) -> String {
-        Self::lines(partial)
-            .iter()
-            .map(|line| indent.to_owned() + line)
-            .collect::<Vec<String>>()
-            .join("")
-    }
-
-    fn lines(input: &str) -> Vec<&str> {
-        let mut lines = Vec::new();
-        let mut line_start = 0;
-        let mut line_len = 0;
-        for c in input.chars() {
-            line_len += 1;
-            if c == '\n' {
-                lines.push(&input[line_start..line_start + line_len]);
-                line_start = line_start + line_len;
-                line_len = 0;
-            }
+    /// Returns the settings made by the pragmas of the template.
+    pub(crate) fn settings(&self) -> Settings {
+        match &self.root {
+            Node::Root(root) => Settings::new(&root.pragmas),
+            _ => Settings::default(),
         }
-        if line_len > 0 {
-            lines.push(&input[line_start..line_start + line_len]);
-        }
-        lines
+    }
+
+    pub(crate) fn program(&self) -> &Program<'t> {
+        self.program.get_or_init(|| Program::lower(&self.root))
     }
 }
@@ -0,0 +1,350 @@
+//! Rendering support for typed templates.
+//!
+//! `#[derive(Template)]` from the `stache-macros` crate generates a `render`
+//! method that reads the fields of a struct directly instead of building a
+//! [`Context`]. Values found inside sections are looked up through the
+//! [`Value`] trait, which is implemented for strings, numbers, booleans,
+//! options, lists and maps, and which `#[derive(Value)]` implements for
+//! structs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+
+use crate::context::Context;
+use crate::render::escape_byte;
+
+/// A value that a typed template can interpolate, look names up in or render a
+/// section for, following the same rules as a [`Context`].
+pub trait Value {
+    /// Writes the value as interpolated text. Lists and maps write nothing.
+    fn write(&self, _out: &mut String, _escaped: bool) {}
+
+    /// Whether a section for the value renders, or an inverted section does
+    /// not.
+    fn is_truthy(&self) -> bool {
+        true
+    }
+
+    /// Looks up a name in the value, if it is a map or a struct.
+    fn get(&self, _name: &str) -> Option<&dyn Value> {
+        None
+    }
+
+    /// Returns the number of items if the value is a list, whose sections
+    /// render once for each item.
+    fn item_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns an item of a list.
+    fn item(&self, _index: usize) -> Option<&dyn Value> {
+        None
+    }
+}
+
+/// Writes text to the output, escaping it for HTML if asked to.
+pub fn push_text(out: &mut String, text: &str, escaped: bool) {
+    if !escaped {
+        out.push_str(text);
+        return;
+    }
+    let mut start = 0;
+    for (i, byte) in text.bytes().enumerate() {
+        if let Some(escape) = escape_byte(byte) {
+            out.push_str(&text[start..i]);
+            out.push_str(escape);
+            start = i + 1;
+        }
+    }
+    out.push_str(&text[start..]);
+}
+
+/// Writes the value of a lookup, writing nothing if it failed.
+pub fn write(value: Option<&dyn Value>, out: &mut String, escaped: bool) {
+    if let Some(value) = value {
+        value.write(out, escaped);
+    }
+}
+
+/// Looks up the keys of a dotted name one after another, starting from a
+/// value.
+pub fn path<'v>(value: &'v dyn Value, keys: &[&str]) -> Option<&'v dyn Value> {
+    keys.iter().try_fold(value, |value, key| value.get(key))
+}
+
+/// A value whose names are looked up by a function, such as the fields and
+/// methods of the struct a typed template is bound to.
+pub struct Lookup<'v, F> {
+    lookup: F,
+    values: PhantomData<&'v dyn Value>,
+}
+
+impl<'v, F: Fn(&str) -> Option<&'v dyn Value>> Lookup<'v, F> {
+    pub fn new(lookup: F) -> Self {
+        Self {
+            lookup,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<'v, F: Fn(&str) -> Option<&'v dyn Value>> Value for Lookup<'v, F> {
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        (self.lookup)(name)
+    }
+}
+
+/// What a name resolves to when the struct a typed template is bound to has
+/// neither a field nor a method of that name. It is falsy and writes nothing,
+/// as a failed lookup does.
+#[doc(hidden)]
+pub struct Missing;
+
+impl Value for Missing {
+    fn is_truthy(&self) -> bool {
+        false
+    }
+}
+
+/// The stack of values names are looked up in while rendering sections.
+pub struct Scope<'v> {
+    stack: Vec<&'v dyn Value>,
+}
+
+impl<'v> Scope<'v> {
+    pub fn new(root: &'v dyn Value) -> Self {
+        Self { stack: vec![root] }
+    }
+
+    /// Finds a name given as its keys, where no keys stand for `.`.
+    pub fn find(&self, keys: &[&str]) -> Option<&'v dyn Value> {
+        let Some((first, rest)) = keys.split_first() else {
+            return self.stack.last().copied();
+        };
+        let value = self
+            .stack
+            .iter()
+            .rev()
+            .copied()
+            .find_map(|value| value.get(first))?;
+        path(value, rest)
+    }
+
+    /// Renders the body of a section once for each item of a list, or once
+    /// for any other truthy value, with the item or value in scope.
+    pub fn section(
+        &mut self,
+        value: Option<&'v dyn Value>,
+        out: &mut String,
+        mut body: impl FnMut(&mut Self, &mut String),
+    ) {
+        let Some(value) = value else {
+            return;
+        };
+        match value.item_count() {
+            Some(count) => {
+                for item in (0..count).filter_map(|index| value.item(index)) {
+                    self.stack.push(item);
+                    body(self, out);
+                    self.stack.pop();
+                }
+            }
+            None if value.is_truthy() => {
+                self.stack.push(value);
+                body(self, out);
+                self.stack.pop();
+            }
+            None => (),
+        }
+    }
+
+    /// Renders the body of an inverted section if the value is missing or
+    /// falsy.
+    pub fn inverted(
+        &mut self,
+        value: Option<&'v dyn Value>,
+        out: &mut String,
+        mut body: impl FnMut(&mut Self, &mut String),
+    ) {
+        if value.is_none_or(|value| !value.is_truthy()) {
+            body(self, out);
+        }
+    }
+}
+
+impl Value for str {
+    fn write(&self, out: &mut String, escaped: bool) {
+        push_text(out, self, escaped);
+    }
+}
+
+impl Value for String {
+    fn write(&self, out: &mut String, escaped: bool) {
+        push_text(out, self, escaped);
+    }
+}
+
+impl Value for bool {
+    fn write(&self, out: &mut String, escaped: bool) {
+        push_text(out, &self.to_string(), escaped);
+    }
+
+    fn is_truthy(&self) -> bool {
+        *self
+    }
+}
+
+macro_rules! impl_value_for_numbers {
+    ($($ty:ty),*) => {
+        $(
+            impl Value for $ty {
+                fn write(&self, out: &mut String, escaped: bool) {
+                    push_text(out, &self.to_string(), escaped);
+                }
+            }
+        )*
+    };
+}
+
+impl_value_for_numbers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl<T: Value + ?Sized> Value for &T {
+    fn write(&self, out: &mut String, escaped: bool) {
+        (**self).write(out, escaped);
+    }
+
+    fn is_truthy(&self) -> bool {
+        (**self).is_truthy()
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        (**self).get(name)
+    }
+
+    fn item_count(&self) -> Option<usize> {
+        (**self).item_count()
+    }
+
+    fn item(&self, index: usize) -> Option<&dyn Value> {
+        (**self).item(index)
+    }
+}
+
+impl<T: Value + ?Sized> Value for Box<T> {
+    fn write(&self, out: &mut String, escaped: bool) {
+        (**self).write(out, escaped);
+    }
+
+    fn is_truthy(&self) -> bool {
+        (**self).is_truthy()
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        (**self).get(name)
+    }
+
+    fn item_count(&self) -> Option<usize> {
+        (**self).item_count()
+    }
+
+    fn item(&self, index: usize) -> Option<&dyn Value> {
+        (**self).item(index)
+    }
+}
+
+/// `None` behaves like a null value and `Some` like the value it holds.
+impl<T: Value> Value for Option<T> {
+    fn write(&self, out: &mut String, escaped: bool) {
+        if let Some(value) = self {
+            value.write(out, escaped);
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        self.as_ref().is_some_and(Value::is_truthy)
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        self.as_ref()?.get(name)
+    }
+
+    fn item_count(&self) -> Option<usize> {
+        self.as_ref()?.item_count()
+    }
+
+    fn item(&self, index: usize) -> Option<&dyn Value> {
+        self.as_ref()?.item(index)
+    }
+}
+
+impl<T: Value> Value for [T] {
+    fn is_truthy(&self) -> bool {
+        !self.is_empty()
+    }
+
+    fn item_count(&self) -> Option<usize> {
+        Some(self.len())
+    }
+
+    fn item(&self, index: usize) -> Option<&dyn Value> {
+        self.get(index).map(|item| item as &dyn Value)
+    }
+}
+
+impl<T: Value> Value for Vec<T> {
+    fn is_truthy(&self) -> bool {
+        self.as_slice().is_truthy()
+    }
+
+    fn item_count(&self) -> Option<usize> {
+        self.as_slice().item_count()
+    }
+
+    fn item(&self, index: usize) -> Option<&dyn Value> {
+        self.as_slice().item(index)
+    }
+}
+
+impl<V: Value> Value for HashMap<String, V> {
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        HashMap::get(self, name).map(|value| value as &dyn Value)
+    }
+}
+
+impl<V: Value> Value for BTreeMap<String, V> {
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        BTreeMap::get(self, name).map(|value| value as &dyn Value)
+    }
+}
+
+impl Value for Context {
+    fn write(&self, out: &mut String, escaped: bool) {
+        match self {
+            Context::String(text) => push_text(out, text, escaped),
+            Context::Map(_) | Context::List(_) => (),
+            scalar => push_text(out, &scalar.to_text(), escaped),
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        Context::is_truthy(self)
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Value> {
+        Context::get(self, name).map(|value| value as &dyn Value)
+    }
+
+    fn item_count(&self) -> Option<usize> {
+        match self {
+            Context::List(list) => Some(list.len()),
+            _ => None,
+        }
+    }
+
+    fn item(&self, index: usize) -> Option<&dyn Value> {
+        match self {
+            Context::List(list) => list.as_slice().get(index).map(|item| item as &dyn Value),
+            _ => None,
+        }
+    }
+}
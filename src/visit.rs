@@ -0,0 +1,119 @@
+//! Traversal of the syntax tree.
+//!
+//! Implement [`Visitor`] or [`VisitorMut`] and override the methods for the
+//! nodes of interest. The default methods descend into children through the
+//! `walk_*` functions, which overriding methods can call to keep descending.
+//!
+//! ```
+//! use stache::ast::Variable;
+//! use stache::visit::Visitor;
+//! use stache::Template;
+//!
+//! struct Names(Vec<String>);
+//!
+//! impl<'t> Visitor<'t> for Names {
+//!     fn visit_variable(&mut self, variable: &Variable<'t>) {
+//!         self.0.push(variable.name.to_string());
+//!     }
+//! }
+//!
+//! let template = Template::compile("{{#items}}{{name}}{{/items}}").unwrap();
+//! let mut names = Names(Vec::new());
+//! names.visit_node(template.ast());
+//! assert_eq!(names.0, ["name"]);
+//! ```
+
+use crate::ast::{Extension, Node, Partial, Root, Section, Text, Variable};
+
+pub trait Visitor<'t> {
+    fn visit_node(&mut self, node: &Node<'t>) {
+        walk_node(self, node);
+    }
+
+    fn visit_root(&mut self, root: &Root<'t>) {
+        walk_root(self, root);
+    }
+
+    fn visit_section(&mut self, section: &Section<'t>) {
+        walk_section(self, section);
+    }
+
+    fn visit_variable(&mut self, _variable: &Variable<'t>) {}
+
+    fn visit_partial(&mut self, _partial: &Partial<'t>) {}
+
+    fn visit_text(&mut self, _text: &Text<'t>) {}
+
+    fn visit_extension(&mut self, _extension: &Extension<'t>) {}
+}
+
+pub fn walk_node<'t, V: Visitor<'t> + ?Sized>(visitor: &mut V, node: &Node<'t>) {
+    match node {
+        Node::Root(root) => visitor.visit_root(root),
+        Node::Section(section) => visitor.visit_section(section),
+        Node::Variable(variable) => visitor.visit_variable(variable),
+        Node::Partial(partial) => visitor.visit_partial(partial),
+        Node::Text(text) => visitor.visit_text(text),
+        Node::Extension(extension) => visitor.visit_extension(extension),
+    }
+}
+
+pub fn walk_root<'t, V: Visitor<'t> + ?Sized>(visitor: &mut V, root: &Root<'t>) {
+    for child in &root.children {
+        visitor.visit_node(child);
+    }
+}
+
+pub fn walk_section<'t, V: Visitor<'t> + ?Sized>(visitor: &mut V, section: &Section<'t>) {
+    for child in &section.children {
+        visitor.visit_node(child);
+    }
+}
+
+pub trait VisitorMut<'t> {
+    fn visit_node_mut(&mut self, node: &mut Node<'t>) {
+        walk_node_mut(self, node);
+    }
+
+    fn visit_root_mut(&mut self, root: &mut Root<'t>) {
+        walk_root_mut(self, root);
+    }
+
+    fn visit_section_mut(&mut self, section: &mut Section<'t>) {
+        walk_section_mut(self, section);
+    }
+
+    fn visit_variable_mut(&mut self, _variable: &mut Variable<'t>) {}
+
+    fn visit_partial_mut(&mut self, _partial: &mut Partial<'t>) {}
+
+    fn visit_text_mut(&mut self, _text: &mut Text<'t>) {}
+
+    fn visit_extension_mut(&mut self, _extension: &mut Extension<'t>) {}
+}
+
+pub fn walk_node_mut<'t, V: VisitorMut<'t> + ?Sized>(visitor: &mut V, node: &mut Node<'t>) {
+    match node {
+        Node::Root(root) => visitor.visit_root_mut(root),
+        Node::Section(section) => visitor.visit_section_mut(section),
+        Node::Variable(variable) => visitor.visit_variable_mut(variable),
+        Node::Partial(partial) => visitor.visit_partial_mut(partial),
+        Node::Text(text) => visitor.visit_text_mut(text),
+        Node::Extension(extension) => visitor.visit_extension_mut(extension),
+    }
+}
+
+pub fn walk_root_mut<'t, V: VisitorMut<'t> + ?Sized>(visitor: &mut V, root: &mut Root<'t>) {
+    for child in &mut root.children {
+        visitor.visit_node_mut(child);
+    }
+}
+
+pub fn walk_section_mut<'t, V: VisitorMut<'t> + ?Sized>(
+    visitor: &mut V,
+    section: &mut Section<'t>,
+) {
+    for child in &mut section.children {
+        visitor.visit_node_mut(child);
+    }
+}
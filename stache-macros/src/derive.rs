@@ -0,0 +1,377 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::path::PathBuf;
+
+use proc_macro2::{Span as Span2, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned};
+use stache::ast::{Name, Node, Section, Variant};
+use stache::Template;
+use syn::{Data, DeriveInput, Fields, Ident, LitStr};
+
+/// Where the source of a typed template comes from.
+enum Source {
+    Path(LitStr),
+    Inline(LitStr),
+}
+
+pub fn template(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let (source, span) = source(&input)?;
+    let (text, tracked) = match &source {
+        Source::Inline(literal) => (literal.value(), None),
+        Source::Path(literal) => {
+            let root = env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from);
+            let path = root.join(literal.value());
+            let text = std::fs::read_to_string(&path).map_err(|error| {
+                let message = format!("failed to read {}: {error}", path.display());
+                syn::Error::new(literal.span(), message)
+            })?;
+            (text, Some(path.to_string_lossy().into_owned()))
+        }
+    };
+    let template = Template::compile(&text).map_err(|error| {
+        let message = match &source {
+            Source::Path(literal) => format!("{}: {error}", literal.value()),
+            Source::Inline(_) => error.to_string(),
+        };
+        syn::Error::new(span, message)
+    })?;
+
+    let Node::Root(root) = template.ast() else {
+        unreachable!("templates compile to a root");
+    };
+    if let Some(pragma) = root.pragmas.first() {
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "typed templates cannot use pragmas, found `{{{{%{}}}}}`",
+                pragma.name
+            ),
+        ));
+    }
+
+    let fields = fields(&input)?;
+    let mut generator = Generator {
+        fields: &fields,
+        span,
+        names: BTreeSet::new(),
+        root: false,
+    };
+    let body = generator.top(&root.children)?;
+    let root = generator.root.then(|| generator.root());
+    // `include_str!` tells the compiler that the crate depends on the file, so
+    // that editing the template triggers a rebuild.
+    let tracked = tracked.map(|path| {
+        quote!(
+            const _: &str = include_str!(#path);
+        )
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Renders the template with the fields of this value.
+            pub fn render(&self) -> ::std::string::String {
+                #tracked
+                #root
+                let mut buffer = ::std::string::String::new();
+                let out = &mut buffer;
+                #body
+                buffer
+            }
+        }
+    })
+}
+
+pub fn value(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(&input)?;
+    let arms = fields.iter().map(|field| {
+        let name = field.to_string();
+        quote!(#name => ::std::option::Option::Some(&self.#field))
+    });
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::stache::typed::Value for #name #ty_generics #where_clause {
+            fn get(&self, name: &str) -> ::std::option::Option<&dyn ::stache::typed::Value> {
+                match name {
+                    #(#arms,)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+fn source(input: &DeriveInput) -> syn::Result<(Source, Span2)> {
+    let mut source = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("template") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            let literal: LitStr = meta.value()?.parse()?;
+            if meta.path.is_ident("path") {
+                source = Some((Source::Path(literal.clone()), literal.span()));
+            } else if meta.path.is_ident("source") {
+                source = Some((Source::Inline(literal.clone()), literal.span()));
+            } else {
+                return Err(meta.error("expected `path` or `source`"));
+            }
+            Ok(())
+        })?;
+    }
+    source.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "expected a `#[template(path = \"...\")]` or `#[template(source = \"...\")]` attribute",
+        )
+    })
+}
+
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Ident>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields
+                .named
+                .iter()
+                .filter_map(|field| field.ident.clone())
+                .collect()),
+            Fields::Unit => Ok(Vec::new()),
+            Fields::Unnamed(_) => Err(syn::Error::new(
+                input.ident.span(),
+                "typed templates need a struct with named fields",
+            )),
+        },
+        _ => Err(syn::Error::new(
+            input.ident.span(),
+            "typed templates need a struct with named fields",
+        )),
+    }
+}
+
+/// Generates the code that renders a template. Names outside any section are
+/// read from the fields, or methods, of the struct directly; names inside
+/// sections are looked up through a `Scope` so that they follow the scoping
+/// rules of `Template::render`.
+struct Generator<'a> {
+    fields: &'a [Ident],
+    span: Span2,
+    /// The first key of every name in the template.
+    names: BTreeSet<String>,
+    /// Whether the struct itself is needed as a value, at the bottom of the
+    /// scope of a section or for `.` outside any section.
+    root: bool,
+}
+
+impl Generator<'_> {
+    fn top(&mut self, nodes: &[Node]) -> syn::Result<TokenStream2> {
+        let mut code = TokenStream2::new();
+        for node in nodes {
+            code.extend(match node {
+                Node::Variable(variable) => {
+                    if let Some(filter) = variable.filters.first() {
+                        return Err(self.error(format!(
+                            "typed templates cannot use filters, found `| {}`",
+                            filter.name
+                        )));
+                    }
+                    let escaped = variable.escaped;
+                    match self.member(&variable.name)? {
+                        None => {
+                            self.root = true;
+                            quote!(::stache::typed::Value::write(&root, out, #escaped);)
+                        }
+                        Some((member, rest)) => quote! {
+                            ::stache::typed::write(
+                                ::stache::typed::path(&#member, &[#(#rest),*]),
+                                out,
+                                #escaped,
+                            );
+                        },
+                    }
+                }
+                Node::Section(section) => {
+                    self.supported(section)?;
+                    self.root = true;
+                    let value = match self.member(&section.name)? {
+                        None => quote!(::std::option::Option::Some(&root)),
+                        Some((member, rest)) => {
+                            quote!(::stache::typed::path(&#member, &[#(#rest),*]))
+                        }
+                    };
+                    let method = Self::method(section.variant);
+                    let body = self.scoped(&section.children)?;
+                    quote! {
+                        ::stache::typed::Scope::new(&root).#method(#value, out, |scope, out| {
+                            #body
+                        });
+                    }
+                }
+                node => self.leaf(node)?,
+            });
+        }
+        Ok(code)
+    }
+
+    fn scoped(&mut self, nodes: &[Node]) -> syn::Result<TokenStream2> {
+        let mut code = TokenStream2::new();
+        for node in nodes {
+            code.extend(match node {
+                Node::Variable(variable) => {
+                    if let Some(filter) = variable.filters.first() {
+                        return Err(self.error(format!(
+                            "typed templates cannot use filters, found `| {}`",
+                            filter.name
+                        )));
+                    }
+                    let escaped = variable.escaped;
+                    let keys = self.keys(&variable.name)?;
+                    quote!(::stache::typed::write(scope.find(&[#(#keys),*]), out, #escaped);)
+                }
+                Node::Section(section) => {
+                    self.supported(section)?;
+                    let keys = self.keys(&section.name)?;
+                    let method = Self::method(section.variant);
+                    let body = self.scoped(&section.children)?;
+                    quote! {
+                        let value = scope.find(&[#(#keys),*]);
+                        scope.#method(value, out, |scope, out| {
+                            #body
+                        });
+                    }
+                }
+                node => self.leaf(node)?,
+            });
+        }
+        Ok(code)
+    }
+
+    fn leaf(&self, node: &Node) -> syn::Result<TokenStream2> {
+        match node {
+            Node::Text(text) => {
+                let text = text.text;
+                Ok(quote!(out.push_str(#text);))
+            }
+            Node::Partial(partial) => Err(self.error(format!(
+                "typed templates cannot include partials, found `{{{{>{}}}}}`",
+                partial.name
+            ))),
+            Node::Extension(_) => Err(self.error("typed templates cannot use extension tags")),
+            _ => unreachable!("sections and variables are generated by the caller"),
+        }
+    }
+
+    /// Rejects sections that call helpers, which typed templates cannot run.
+    fn supported(&self, section: &Section) -> syn::Result<()> {
+        match section.args.is_empty() {
+            true => Ok(()),
+            false => Err(self.error(format!(
+                "typed templates cannot use helpers, found `{{{{#{} ...}}}}`",
+                section.name
+            ))),
+        }
+    }
+
+    /// Generates `root`, the value at the bottom of every scope, which looks
+    /// names up among the fields and methods of the struct. A local trait
+    /// gives every name a fallback method returning `Missing`, which the
+    /// methods of the struct take precedence over, so that names inside
+    /// sections need not be methods of the struct. Methods are called at most
+    /// once, when a lookup first reaches them.
+    fn root(&self) -> TokenStream2 {
+        let mut arms = Vec::new();
+        let mut cells = Vec::new();
+        let mut fallbacks = Vec::new();
+        for name in &self.names {
+            let Ok(ident) = syn::parse_str::<Ident>(name) else {
+                continue;
+            };
+            if self.fields.contains(&ident) {
+                arms.push(quote!(#name => ::std::option::Option::Some(&self.#ident)));
+                continue;
+            }
+            let cell = format_ident!("method_{}", ident);
+            arms.push(quote! {
+                #name => ::std::option::Option::Some(#cell.get_or_init(|| self.#ident()))
+            });
+            cells.push(quote!(let #cell = ::std::cell::OnceCell::new();));
+            fallbacks.push(quote! {
+                fn #ident(&self) -> ::stache::typed::Missing {
+                    ::stache::typed::Missing
+                }
+            });
+        }
+        quote! {
+            #(#cells)*
+            let root = {
+                #[allow(dead_code, non_snake_case)]
+                trait Fallback {
+                    #(#fallbacks)*
+                }
+                impl<T: ?::std::marker::Sized> Fallback for T {}
+                ::stache::typed::Lookup::new(
+                    |name: &str| -> ::std::option::Option<&dyn ::stache::typed::Value> {
+                        match name {
+                            #(#arms,)*
+                            _ => ::std::option::Option::None,
+                        }
+                    },
+                )
+            };
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> syn::Error {
+        syn::Error::new(self.span, message)
+    }
+
+    /// Resolves the first key of a name outside any section to a field of the
+    /// struct, or to a method call that the compiler checks. Returns `None`
+    /// for `.`, which stands for the struct itself.
+    fn member(&mut self, name: &Name) -> syn::Result<Option<(TokenStream2, Vec<String>)>> {
+        let keys = self.keys(name)?;
+        let Some((first, rest)) = keys.split_first() else {
+            return Ok(None);
+        };
+        let ident = syn::parse_str::<Ident>(first).map_err(|_| {
+            syn::Error::new(
+                self.span,
+                format!("`{first}` is not a field or method name"),
+            )
+        })?;
+        let member = match self.fields.contains(&ident) {
+            true => quote!(self.#ident),
+            false => {
+                let ident = format_ident!("{}", first, span = self.span);
+                quote_spanned!(self.span=> self.#ident())
+            }
+        };
+        Ok(Some((member, rest.to_vec())))
+    }
+
+    fn keys(&mut self, name: &Name) -> syn::Result<Vec<String>> {
+        let keys: Vec<String> = match name {
+            Name::Implicit => Vec::new(),
+            Name::Key(key) => vec![key.to_string()],
+            Name::Path(keys) => keys.iter().map(|key| key.to_string()).collect(),
+            Name::Parent(..) | Name::Root(_) | Name::Anchored(_) => {
+                return Err(syn::Error::new(
+                    self.span,
+                    format!("typed templates cannot use scoped names, found `{name}`"),
+                ))
+            }
+        };
+        if let Some(first) = keys.first() {
+            self.names.insert(first.clone());
+        }
+        Ok(keys)
+    }
+
+    fn method(variant: Variant) -> Ident {
+        match variant {
+            Variant::Direct => format_ident!("section"),
+            Variant::Inverse => format_ident!("inverted"),
+        }
+    }
+}
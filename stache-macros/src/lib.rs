@@ -0,0 +1,350 @@
+//! Templates compiled along with the crate that uses them.
+//!
+//! [`stache!`] parses a template literal and [`include_template!`] a template
+//! file while the crate is being compiled, with the same lexer and parser as
+//! [`Template::compile`](stache::Template::compile). Syntax errors are
+//! reported as compiler errors, and the macros expand to a
+//! `&'static Template<'static>` whose syntax tree is built without parsing at
+//! runtime.
+//!
+//! ```
+//! use stache::MapBuilder;
+//! use stache_macros::stache;
+//!
+//! let template = stache!("Hello {{name}}!");
+//! let context = MapBuilder::new().str("name", "Mustafa").build();
+//! assert_eq!(template.render(context).unwrap(), "Hello Mustafa!");
+//! ```
+//!
+//! [`derive@Template`] binds a struct to a template instead, generating a
+//! `render` method that reads the struct's fields without building a
+//! `Context`.
+
+use std::env;
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use stache::ast::{ArgumentValue, Name, Node, Span, Variant};
+use stache::Template;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+mod derive;
+
+/// Compiles a template literal.
+///
+/// ```compile_fail
+/// use stache_macros::stache;
+///
+/// let template = stache!("{{#items}}{{/item}}");
+/// ```
+#[proc_macro]
+pub fn stache(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let text = literal.value();
+    match Template::compile(&text) {
+        Ok(template) => expand(&text, quote!(#literal), template.ast()).into(),
+        Err(error) => {
+            let span = literal_span(&literal, &text, &error);
+            syn::Error::new(span, error).to_compile_error().into()
+        }
+    }
+}
+
+/// Compiles a template file, given by a path relative to the directory of the
+/// crate's `Cargo.toml`. The crate is rebuilt whenever the file changes.
+#[proc_macro]
+pub fn include_template(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let root = env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from);
+    let path = root.join(literal.value());
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) => {
+            let message = format!("failed to read {}: {error}", path.display());
+            return syn::Error::new(literal.span(), message)
+                .to_compile_error()
+                .into();
+        }
+    };
+    match Template::compile(&text) {
+        Ok(template) => {
+            let path = path.to_string_lossy();
+            // `include_str!` tells the compiler that the crate depends on the
+            // file, so that editing the template triggers a rebuild.
+            expand(&text, quote!(include_str!(#path)), template.ast()).into()
+        }
+        Err(error) => {
+            let message = format!("{}: {error}", literal.value());
+            syn::Error::new(literal.span(), message)
+                .to_compile_error()
+                .into()
+        }
+    }
+}
+
+/// Binds a struct to a template given by `#[template(path = "...")]`, relative
+/// to the directory of the crate's `Cargo.toml`, or by
+/// `#[template(source = "...")]`.
+///
+/// The generated `render(&self) -> String` method reads names outside any
+/// section from the struct's fields, or calls methods of the same name, so
+/// that a name the struct does not provide fails to compile. Inside sections,
+/// names are looked up in the section's value first and then in the enclosing
+/// values, as with `Template::render`, ending with the fields and methods of
+/// the struct; values found there implement [`stache::typed::Value`], which
+/// [`derive@Value`] implements for structs. Typed templates cannot include
+/// partials or use pragmas, filters, helpers or extension tags, which fail to
+/// compile rather than render differently from `Template::render`.
+///
+/// ```
+/// use stache_macros::{Template, Value};
+///
+/// #[derive(Value)]
+/// struct Item {
+///     name: String,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(source = "{{title}}:{{#items}} {{name}}{{/items}}")]
+/// struct List {
+///     items: Vec<Item>,
+/// }
+///
+/// impl List {
+///     fn title(&self) -> &str {
+///         "Items"
+///     }
+/// }
+///
+/// let list = List {
+///     items: vec![Item { name: "a".into() }, Item { name: "b".into() }],
+/// };
+/// assert_eq!(list.render(), "Items: a b");
+/// ```
+///
+/// ```compile_fail
+/// use stache_macros::Template;
+///
+/// #[derive(Template)]
+/// #[template(source = "Hello {{name}}!")]
+/// struct Greeting {
+///     nickname: String,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use stache_macros::Template;
+///
+/// #[derive(Template)]
+/// #[template(source = "{{%FILTERS}}Hello {{name | upper}}!")]
+/// struct Greeting {
+///     name: String,
+/// }
+/// ```
+#[proc_macro_derive(Template, attributes(template))]
+pub fn derive_template(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::template(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements [`stache::typed::Value`] for a struct with named fields, so
+/// that sections of typed templates can look up its fields.
+#[proc_macro_derive(Value)]
+pub fn derive_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::value(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Expands to a lazily built static template holding the given syntax tree.
+fn expand(text: &str, source: TokenStream2, root: &Node) -> TokenStream2 {
+    let root = node(text, root);
+    quote! {{
+        const TEXT: &str = #source;
+        static TEMPLATE: ::std::sync::LazyLock<::stache::Template<'static>> =
+            ::std::sync::LazyLock::new(|| ::stache::Template::from_ast(TEXT, #root));
+        &*TEMPLATE
+    }}
+}
+
+fn node(text: &str, node: &Node) -> TokenStream2 {
+    match node {
+        Node::Root(root) => {
+            let children = root.children.iter().map(|child| self::node(text, child));
+            let pragmas = root.pragmas.iter().map(|pragma| {
+                let name = pragma.name;
+                let options = pragma
+                    .options
+                    .iter()
+                    .map(|(key, value)| quote!((#key, #value)));
+                let span = span(pragma.span);
+                quote!(::stache::ast::Pragma {
+                    name: #name,
+                    options: vec![#(#options),*],
+                    span: #span,
+                })
+            });
+            quote! {
+                ::stache::ast::Node::Root(::stache::ast::Root {
+                    children: vec![#(#children),*],
+                    pragmas: vec![#(#pragmas),*],
+                })
+            }
+        }
+        Node::Section(section) => {
+            let name = name(&section.name);
+            let variant = match section.variant {
+                Variant::Direct => quote!(::stache::ast::Variant::Direct),
+                Variant::Inverse => quote!(::stache::ast::Variant::Inverse),
+            };
+            let children = section.children.iter().map(|child| self::node(text, child));
+            let open = span(section.open);
+            let close = span(section.close);
+            let args = section.args.iter().map(|arg| {
+                let key = match arg.key {
+                    Some(key) => quote!(::std::option::Option::Some(#key)),
+                    None => quote!(::std::option::Option::None),
+                };
+                let value = match &arg.value {
+                    ArgumentValue::Name(name) => {
+                        let name = self::name(name);
+                        quote!(::stache::ast::ArgumentValue::Name(#name))
+                    }
+                    ArgumentValue::String(text) => {
+                        quote!(::stache::ast::ArgumentValue::String(#text))
+                    }
+                    ArgumentValue::Number(number) => {
+                        quote!(::stache::ast::ArgumentValue::Number(#number))
+                    }
+                };
+                quote!(::stache::ast::Argument {
+                    key: #key,
+                    value: #value,
+                })
+            });
+            quote! {
+                ::stache::ast::Node::Section(::stache::ast::Section {
+                    name: #name,
+                    variant: #variant,
+                    children: vec![#(#children),*],
+                    open: #open,
+                    close: #close,
+                    args: vec![#(#args),*],
+                })
+            }
+        }
+        Node::Variable(variable) => {
+            let name = name(&variable.name);
+            let escaped = variable.escaped;
+            let filters = variable.filters.iter().map(|filter| {
+                let name = filter.name;
+                let args = &filter.args;
+                quote!(::stache::ast::Filter {
+                    name: #name,
+                    args: vec![#(#args),*],
+                })
+            });
+            let span = span(variable.span);
+            quote! {
+                ::stache::ast::Node::Variable(::stache::ast::Variable {
+                    name: #name,
+                    escaped: #escaped,
+                    filters: vec![#(#filters),*],
+                    span: #span,
+                })
+            }
+        }
+        Node::Partial(partial) => {
+            let name = partial.name;
+            let dynamic = match &partial.dynamic {
+                Some(dynamic) => {
+                    let dynamic = self::name(dynamic);
+                    quote!(::std::option::Option::Some(#dynamic))
+                }
+                None => quote!(::std::option::Option::None),
+            };
+            let indent = &partial.indent;
+            let span = span(partial.span);
+            quote! {
+                ::stache::ast::Node::Partial(::stache::ast::Partial {
+                    name: #name,
+                    dynamic: #dynamic,
+                    indent: ::std::string::String::from(#indent),
+                    span: #span,
+                })
+            }
+        }
+        Node::Text(node) => {
+            let Span { start, end } = node.span;
+            let span = span(node.span);
+            // Text borrows from the source rather than repeating it.
+            let value = match text.get(start..end) {
+                Some(value) if value == node.text => quote!(&TEXT[#start..#end]),
+                _ => {
+                    let value = node.text;
+                    quote!(#value)
+                }
+            };
+            quote! {
+                ::stache::ast::Node::Text(::stache::ast::Text {
+                    text: #value,
+                    span: #span,
+                })
+            }
+        }
+        Node::Extension(_) => unreachable!("templates are parsed without extensions"),
+    }
+}
+
+fn name(name: &Name) -> TokenStream2 {
+    match name {
+        Name::Implicit => quote!(::stache::ast::Name::Implicit),
+        Name::Key(key) => quote!(::stache::ast::Name::Key(#key)),
+        Name::Path(keys) => quote!(::stache::ast::Name::Path(vec![#(#keys),*])),
+        Name::Parent(levels, inner) => {
+            let inner = self::name(inner);
+            quote!(::stache::ast::Name::Parent(#levels, ::std::boxed::Box::new(#inner)))
+        }
+        Name::Root(inner) => {
+            let inner = self::name(inner);
+            quote!(::stache::ast::Name::Root(::std::boxed::Box::new(#inner)))
+        }
+        Name::Anchored(inner) => {
+            let inner = self::name(inner);
+            quote!(::stache::ast::Name::Anchored(::std::boxed::Box::new(#inner)))
+        }
+    }
+}
+
+fn span(span: Span) -> TokenStream2 {
+    let Span { start, end } = span;
+    quote!(::stache::ast::Span { start: #start, end: #end })
+}
+
+/// Points at the location of an error within a template literal where the
+/// compiler supports it, falling back to the whole literal.
+fn literal_span(literal: &LitStr, text: &str, error: &stache::Error) -> proc_macro2::Span {
+    let (stache::Error::Parse { span, .. } | stache::Error::Name { span, .. }) = error else {
+        return literal.span();
+    };
+    // Offsets only carry over when the literal holds the text verbatim,
+    // without escapes.
+    let token = literal.token().to_string();
+    let Some(quote) = token.find('"') else {
+        return literal.span();
+    };
+    let offset = quote + 1;
+    if !token[offset..].starts_with(text) {
+        return literal.span();
+    }
+    let end = span.end.max(span.start + 1).min(text.len());
+    literal
+        .token()
+        .subspan(offset + span.start..offset + end)
+        .unwrap_or_else(|| literal.span())
+}
@@ -0,0 +1,237 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use stache::{Context, MapBuilder, Template};
+use stache_macros::{Template, Value};
+
+#[derive(Value)]
+struct Owner {
+    name: String,
+    email: &'static str,
+}
+
+#[derive(Value)]
+struct Row {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+}
+
+#[derive(Value)]
+struct Summary {
+    count: usize,
+    ratio: f64,
+    done: bool,
+}
+
+#[derive(Template)]
+#[template(path = "tests/templates/report.mustache")]
+struct Report {
+    title: String,
+    owner: Option<Owner>,
+    rows: Vec<Row>,
+    summary: Summary,
+}
+
+fn report(owner: bool, rows: usize) -> Report {
+    Report {
+        title: String::from("Q3 & Q4"),
+        owner: owner.then(|| Owner {
+            name: String::from("Ada <admin>"),
+            email: "ada@example.com",
+        }),
+        rows: (0..rows as u32)
+            .map(|id| Row {
+                id,
+                name: format!("row \"{id}\""),
+                tags: (0..id).map(|tag| format!("t{tag}")).collect(),
+            })
+            .collect(),
+        summary: Summary {
+            count: rows,
+            ratio: 0.5,
+            done: rows > 0,
+        },
+    }
+}
+
+/// Builds the context `Template::render` needs for the same data.
+fn context(report: &Report) -> Context {
+    let map = |context: Context| match context {
+        Context::Map(map) => map,
+        _ => unreachable!(),
+    };
+    let mut builder = MapBuilder::new().str("title", &report.title);
+    if let Some(owner) = &report.owner {
+        let owner = MapBuilder::new()
+            .str("name", &owner.name)
+            .str("email", owner.email)
+            .build();
+        builder = builder.map("owner", map(owner));
+    }
+    let rows = report
+        .rows
+        .iter()
+        .map(|row| {
+            let tags = row.tags.iter().map(|tag| Context::String(tag.clone()));
+            MapBuilder::new()
+                .int("id", row.id.into())
+                .str("name", &row.name)
+                .list("tags", tags.collect())
+                .build()
+        })
+        .collect();
+    let summary = MapBuilder::new()
+        .int("count", report.summary.count as i64)
+        .float("ratio", report.summary.ratio)
+        .bool("done", report.summary.done)
+        .build();
+    builder
+        .list("rows", rows)
+        .map("summary", map(summary))
+        .build()
+}
+
+#[test]
+fn matches_template_render() {
+    let text = include_str!("templates/report.mustache");
+    let template = Template::compile(text).unwrap();
+    for (owner, rows) in [(true, 3), (false, 0), (true, 0), (false, 1)] {
+        let report = report(owner, rows);
+        let expected = template.render(context(&report)).unwrap();
+        assert_eq!(report.render(), expected);
+    }
+}
+
+#[test]
+fn output() {
+    let report = report(true, 2);
+    let expected = "\
+<h1>Q3 &amp; Q4</h1>
+  Owner: Ada &lt;admin&gt; <ada@example.com>
+  <li>0. row &quot;0&quot; (untagged) in Q3 &amp; Q4</li>
+  <li>1. row &quot;1&quot; [t0] in Q3 &amp; Q4</li>
+Total: 2 rows, 0.5, done: true
+";
+    assert_eq!(report.render(), expected);
+}
+
+#[derive(Template)]
+#[template(source = "{{greeting}}, {{name}}{{#shout}}!{{/shout}}")]
+struct Greeting<'a> {
+    name: &'a str,
+    shout: bool,
+}
+
+impl Greeting<'_> {
+    fn greeting(&self) -> String {
+        format!("Hello from {}", self.name.len())
+    }
+}
+
+#[test]
+fn methods_and_lifetimes() {
+    let greeting = Greeting {
+        name: "Mustafa",
+        shout: true,
+    };
+    assert_eq!(greeting.render(), "Hello from 7, Mustafa!");
+}
+
+#[derive(Template)]
+#[template(source = "{{#items}}{{#.}}{{.}}={{/.}}{{/items}}{{#counts}}{{a}}{{b}}{{/counts}}")]
+struct Nested {
+    items: Vec<Vec<i32>>,
+    counts: HashMap<String, i32>,
+}
+
+#[test]
+fn lists_and_maps() {
+    let nested = Nested {
+        items: vec![vec![1, 2], vec![], vec![3]],
+        counts: HashMap::from([(String::from("a"), 1), (String::from("b"), 2)]),
+    };
+    assert_eq!(nested.render(), "1=2=3=12");
+}
+
+#[derive(Template)]
+#[template(source = "{{#items}}{{label}} {{.}}; {{/items}}{{^items}}{{missing}}none{{/items}}")]
+struct Labels {
+    items: Vec<String>,
+    calls: Cell<usize>,
+}
+
+impl Labels {
+    fn label(&self) -> String {
+        self.calls.set(self.calls.get() + 1);
+        format!("#{}", self.items.len())
+    }
+}
+
+#[test]
+fn methods_in_sections() {
+    let labels = Labels {
+        items: vec![String::from("a"), String::from("b")],
+        calls: Cell::new(0),
+    };
+    assert_eq!(labels.render(), "#2 a; #2 b; ");
+    assert_eq!(labels.calls.get(), 1);
+    let empty = Labels {
+        items: Vec::new(),
+        calls: Cell::new(0),
+    };
+    assert_eq!(empty.render(), "none");
+}
+
+#[derive(Template, Value)]
+#[template(source = "{{name}}: {{#tags}}{{.}} {{/tags}}")]
+struct Tagged {
+    name: String,
+    tags: Vec<String>,
+}
+
+#[derive(Template)]
+#[template(source = "{{#items}}[{{name}}{{#tags}} {{.}}{{/tags}}]{{/items}}")]
+struct Listing {
+    items: Vec<Tagged>,
+}
+
+#[test]
+fn template_and_value() {
+    let tagged = Tagged {
+        name: String::from("a"),
+        tags: vec![String::from("x"), String::from("y")],
+    };
+    assert_eq!(tagged.render(), "a: x y ");
+    let listing = Listing {
+        items: vec![tagged],
+    };
+    assert_eq!(listing.render(), "[a x y]");
+}
+
+#[derive(Template)]
+#[template(source = "|{{items}}|{{{counts}}}|{{&items}}|{{#items}}{{.}}{{/items}}|")]
+struct Containers {
+    items: Vec<i32>,
+    counts: HashMap<String, i32>,
+}
+
+#[test]
+fn maps_and_lists_write_nothing() {
+    let containers = Containers {
+        items: vec![1, 2],
+        counts: HashMap::from([(String::from("a"), 1)]),
+    };
+    let template =
+        Template::compile("|{{items}}|{{{counts}}}|{{&items}}|{{#items}}{{.}}{{/items}}|").unwrap();
+    let context = MapBuilder::new()
+        .list("items", vec![Context::Integer(1), Context::Integer(2)])
+        .map(
+            "counts",
+            HashMap::from([(String::from("a"), Context::Integer(1))]),
+        )
+        .build();
+    let expected = template.render(context).unwrap();
+    assert_eq!(expected, "||||12|");
+    assert_eq!(containers.render(), expected);
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use stache::{MapBuilder, Template, VecBuilder};
+use stache_macros::{include_template, stache};
+
+#[test]
+fn literal() {
+    let template = stache!("Hello {{person.name}}{{#emphasis}}!{{/emphasis}}");
+    let person = MapBuilder::new().str("name", "Mustafa").build();
+    let stache::Context::Map(person) = person else {
+        unreachable!();
+    };
+    let context = MapBuilder::new()
+        .map("person", person)
+        .bool("emphasis", true)
+        .build();
+    assert_eq!(template.render(context).unwrap(), "Hello Mustafa!");
+}
+
+#[test]
+fn file() {
+    let template = include_template!("tests/templates/greeting.mustache");
+    let users = VecBuilder::new()
+        .map(HashMap::from([(
+            String::from("name"),
+            stache::Context::String("Ada".into()),
+        )]))
+        .build();
+    let stache::Context::List(users) = users else {
+        unreachable!();
+    };
+    let context = MapBuilder::new().list("users", users).build();
+    assert_eq!(template.render(context).unwrap(), "  Hello Ada!\n");
+}
+
+#[test]
+fn same_tree() {
+    let text = "{{#a}}\n  {{>b}}\n{{/a}}{{{c}}}{{&d}}{{.}} {{=<% %>=}}<%e.f%>\n";
+    let compiled = Template::compile(text).unwrap();
+    let expanded = stache!("{{#a}}\n  {{>b}}\n{{/a}}{{{c}}}{{&d}}{{.}} {{=<% %>=}}<%e.f%>\n");
+    assert_eq!(expanded.ast(), compiled.ast());
+    assert_eq!(expanded.references(), compiled.references());
+}
+
+#[test]
+fn static_template() {
+    fn greeting() -> &'static Template<'static> {
+        stache!("Hello {{name}}!")
+    }
+    assert!(std::ptr::eq(greeting(), greeting()));
+}
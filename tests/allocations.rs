@@ -0,0 +1,54 @@
+//! Counts the allocations the lexer makes. The counting allocator replaces the
+//! global allocator of this test binary only.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use stache::lexer::Lexer;
+use stache::Result;
+
+/// Counts the allocations made on each thread, so that a test can check how
+/// many allocations the code it runs makes.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn no_allocations() -> Result<()> {
+    let text = "{{#items}}\n  {{{name}}}: {{& value}} {{>partial}}\r\n{{/items}}\n".repeat(1000);
+    let mut lexer = Lexer::new(&text);
+    let before = ALLOCATIONS.with(Cell::get);
+    let mut count = 0;
+    while lexer.next_token()?.is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 11000);
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    Ok(())
+}
+
+#[test]
+fn allocations_per_set_delim() -> Result<()> {
+    let text = "{{=<% %>=}}<%a%><%={{ }}=%>{{b}}".repeat(100);
+    let mut lexer = Lexer::new(&text);
+    let before = ALLOCATIONS.with(Cell::get);
+    while lexer.next_token()?.is_some() {}
+    assert_eq!(ALLOCATIONS.with(Cell::get) - before, 200 * 4);
+    Ok(())
+}
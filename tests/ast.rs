@@ -0,0 +1,189 @@
+use stache::ast::{Name, Node, Section, Span, Variable};
+use stache::visit::{walk_section, Visitor, VisitorMut};
+use stache::{Error, MapBuilder, Position, Result, Template};
+
+#[derive(Default)]
+struct Names {
+    path: Vec<String>,
+    names: Vec<String>,
+}
+
+impl<'t> Visitor<'t> for Names {
+    fn visit_section(&mut self, section: &Section<'t>) {
+        self.path.push(section.name.to_string());
+        walk_section(self, section);
+        self.path.pop();
+    }
+
+    fn visit_variable(&mut self, variable: &Variable<'t>) {
+        let mut name = self.path.join("/");
+        name.push('/');
+        name.push_str(&variable.name.to_string());
+        self.names.push(name);
+    }
+}
+
+struct Rename<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl<'t> VisitorMut<'t> for Rename<'t> {
+    fn visit_variable_mut(&mut self, variable: &mut Variable<'t>) {
+        if variable.name == self.from {
+            variable.name = Name::Key(self.to);
+        }
+    }
+}
+
+#[test]
+fn visitor() -> Result<()> {
+    let template = Template::compile("{{a}}{{#b}}{{c}}{{^d}}{{e}}{{/d}}{{/b}}")?;
+    let mut names = Names::default();
+    names.visit_node(template.ast());
+    assert_eq!(names.names, ["/a", "b/c", "b/d/e"]);
+    Ok(())
+}
+
+#[test]
+fn visitor_mut() -> Result<()> {
+    let mut template = Template::compile("Hello {{name}}!")?;
+    let mut rename = Rename {
+        from: "name",
+        to: "nickname",
+    };
+    rename.visit_node_mut(template.ast_mut());
+    let context = MapBuilder::new()
+        .str("name", "Mustafa")
+        .str("nickname", "Moiz")
+        .build();
+    assert_eq!(template.render(context)?, "Hello Moiz!");
+    Ok(())
+}
+
+#[test]
+fn leaf_children() -> Result<()> {
+    let template = Template::compile("text{{name}}")?;
+    let root = template.ast();
+    assert_eq!(root.children().len(), 2);
+    assert!(root[0].children().is_empty());
+    assert!(matches!(root[1], Node::Variable(_)));
+    Ok(())
+}
+
+#[test]
+fn spans() -> Result<()> {
+    let text = "Hi {{name}}\n{{#items}}\n  {{>item}}\n{{/items}}";
+    let template = Template::compile(text)?;
+    let root = template.ast();
+    let Node::Text(greeting) = &root[0] else {
+        panic!("expected text");
+    };
+    assert_eq!(greeting.span, Span::new(0, 3));
+    let Node::Variable(name) = &root[1] else {
+        panic!("expected variable");
+    };
+    assert_eq!(&text[name.span.start..name.span.end], "{{name}}");
+    let Node::Section(section) = &root[3] else {
+        panic!("expected section");
+    };
+    assert_eq!(&text[section.open.start..section.open.end], "{{#items}}");
+    assert_eq!(&text[section.close.start..section.close.end], "{{/items}}");
+    let Node::Partial(partial) = &section.children[0] else {
+        panic!("expected partial");
+    };
+    assert_eq!(&text[partial.span.start..partial.span.end], "{{>item}}");
+    Ok(())
+}
+
+#[test]
+fn spans_after_set_delimiters() -> Result<()> {
+    let text = "{{=<% %>=}}<%name%> {{kept}}";
+    let template = Template::compile(text)?;
+    let Node::Variable(name) = &template.ast()[0] else {
+        panic!("expected variable");
+    };
+    assert_eq!(name.span, Span::new(11, 19));
+    let Node::Text(kept) = &template.ast()[2] else {
+        panic!("expected text");
+    };
+    assert_eq!(kept.text, "{{kept}}");
+    assert_eq!(kept.span, Span::new(20, 28));
+    Ok(())
+}
+
+#[test]
+fn parse_error_location() {
+    let error = Template::compile("line\n  {{#a}}{{/b}}").err();
+    let expected = Error::Parse {
+        span: Span::new(13, 19),
+        position: Position { line: 2, column: 9 },
+    };
+    assert_eq!(error, Some(expected));
+
+    let error = Template::compile("{{a}} {{b").err();
+    let expected = Error::Parse {
+        span: Span::new(6, 9),
+        position: Position { line: 1, column: 7 },
+    };
+    assert_eq!(error, Some(expected));
+}
+
+#[test]
+fn unclosed_section() {
+    let error = Template::compile("{{#a}}\n  {{#b}}{{c}}{{/b}}\n").err();
+    let expected = Error::Parse {
+        span: Span::new(0, 6),
+        position: Position { line: 1, column: 1 },
+    };
+    assert_eq!(error, Some(expected));
+}
+
+#[test]
+fn names() -> Result<()> {
+    let template = Template::compile("{{.}}{{name}}{{#person.address}}{{/person.address}}")?;
+    let root = template.ast();
+    let Node::Variable(implicit) = &root[0] else {
+        panic!("expected a variable");
+    };
+    assert_eq!(implicit.name, Name::Implicit);
+    let Node::Variable(key) = &root[1] else {
+        panic!("expected a variable");
+    };
+    assert_eq!(key.name, Name::Key("name"));
+    let Node::Section(path) = &root[2] else {
+        panic!("expected a section");
+    };
+    assert_eq!(path.name, Name::Path(vec!["person", "address"]));
+    assert_eq!(path.name, "person.address");
+    assert_eq!(path.name.to_string(), "person.address");
+    Ok(())
+}
+
+#[test]
+fn invalid_names() {
+    for (text, name, start) in [
+        ("Hello {{a..b}}", "a..b", 6),
+        ("{{#.a}}{{/.a}}", ".a", 0),
+        ("\n{{{a.}}}", "a.", 1),
+    ] {
+        let text = text.replace("\\n", "\n");
+        let error = Template::compile(&text).err();
+        let Some(Error::Name {
+            name: found, span, ..
+        }) = error
+        else {
+            panic!("expected a name error for {text:?}");
+        };
+        assert_eq!(found, name);
+        assert_eq!(span.start, start);
+    }
+}
+
+#[test]
+fn invalid_name_message() {
+    let error = Template::compile("{{#list}}\n  {{a..b}}\n{{/list}}")
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "invalid name `a..b` at 2:3");
+}
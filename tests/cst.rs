@@ -0,0 +1,109 @@
+use stache::cst::{SyntaxNode, SyntaxTree, TagKind};
+use stache::{Error, MapBuilder, Result, Template};
+
+const TEMPLATES: [&str; 10] = [
+    "Hello {{name}}!",
+    "{{ name }} {{{ html }}} {{& raw}}\n",
+    "Begin.\n{{! Comment Block! }}\nEnd.\n",
+    "  {{#boolean}}\r\n  {{/boolean}}\r\n",
+    "{{^list}}\n\t{{.}}\n{{/list}}",
+    "{{=<% %>=}}(<%text%>)\n<%={{ }}=%>{{ text }}",
+    "  {{!\n  multi-line\n  }}  \n",
+    "\\\n {{>partial}}\n/\n",
+    "{{#a}}{{#b}}{{c.d}}{{/b}}{{/a}}{{#unclosed}}",
+    "|{{= | | =}}|{{#a}}|",
+];
+
+#[test]
+fn round_trip() -> Result<()> {
+    for text in TEMPLATES {
+        let tree = SyntaxTree::parse(text)?;
+        assert_eq!(tree.to_string(), text);
+    }
+    Ok(())
+}
+
+#[test]
+fn tags() -> Result<()> {
+    let tree = SyntaxTree::parse("{{! note }}{{#a}}{{{ b }}}{{/a}}{{=<% %>=}}<%>c%>")?;
+    let kinds: Vec<(TagKind, &str)> = tree
+        .tags()
+        .iter()
+        .map(|tag| (tag.kind, tag.content.as_ref()))
+        .collect();
+    let expected = vec![
+        (TagKind::Comment, "note"),
+        (TagKind::Section, "a"),
+        (TagKind::Triple, "b"),
+        (TagKind::SectionEnd, "a"),
+        (TagKind::SetDelim, "<% %>"),
+        (TagKind::Partial, "c"),
+    ];
+    assert_eq!(kinds, expected);
+    Ok(())
+}
+
+#[test]
+fn sections() -> Result<()> {
+    let tree = SyntaxTree::parse("{{#a}}x{{/a}}{{#b}}")?;
+    let SyntaxNode::Section(a) = &tree.nodes[0] else {
+        panic!("expected section");
+    };
+    assert_eq!(a.children, vec![SyntaxNode::Text("x".into())]);
+    assert_eq!(a.close.as_ref().map(|x| x.content.as_ref()), Some("a"));
+    let SyntaxNode::Section(b) = &tree.nodes[1] else {
+        panic!("expected section");
+    };
+    assert_eq!(b.close, None);
+    Ok(())
+}
+
+#[test]
+fn mismatched_section() {
+    let tree = SyntaxTree::parse("{{#a}}{{/b}}");
+    assert!(matches!(tree, Err(Error::Parse { .. })));
+}
+
+#[test]
+fn rename() -> Result<()> {
+    let text = "{{#items}}\n  {{ title }}: {{{title}}}\n{{/items}}\n{{title}}";
+    let mut tree = SyntaxTree::parse(text)?;
+    for tag in tree.tags_mut() {
+        if tag.content == "items" {
+            tag.content = "posts".into();
+        }
+    }
+    let edited = tree.to_string();
+    let expected = "{{#posts}}\n  {{ title }}: {{{title}}}\n{{/posts}}\n{{title}}";
+    assert_eq!(edited, expected);
+
+    let template = Template::compile(&edited)?;
+    let context = MapBuilder::new()
+        .list("posts", vec![MapBuilder::new().str("title", "a").build()])
+        .str("title", "b")
+        .build();
+    assert_eq!(template.render(context)?, "  a: a\nb");
+    Ok(())
+}
+
+#[test]
+fn whitespace_control() -> Result<()> {
+    let text = "{{%WHITESPACE-CONTROL}}\n\
+        {{~a~}} {{~ a ~}} {{~&a~}} {{~{a}~}} {{~& a}} {{{ a }~}}\n\
+        {{~#s~}} {{~^t~}} {{~/t~}} {{~/s~}}\n\
+        {{~>p~}} {{~! c ~}} {{=<% %>=}}<%~a~%>\n";
+    let tree = SyntaxTree::parse(text)?;
+    assert_eq!(tree.to_string(), text);
+    let kinds: Vec<(TagKind, bool, bool, &str)> = tree
+        .tags()
+        .into_iter()
+        .map(|tag| (tag.kind, tag.trim_before, tag.trim_after, &*tag.content))
+        .collect();
+    assert_eq!(kinds[3], (TagKind::Unescaped, true, true, "a"));
+    assert_eq!(kinds[4], (TagKind::Triple, true, true, "a"));
+    assert_eq!(kinds[6], (TagKind::Triple, false, true, "a"));
+    assert_eq!(kinds[14], (TagKind::Variable, true, true, "a"));
+    let tree = SyntaxTree::parse("{{~&a~}}")?;
+    assert_eq!(tree.tags()[0].content, "~&a~");
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use stache::{CompileOptions, Context, MapBuilder, Partials, RenderOptions, Sigils};
+use stache::{Error, Template};
+
+mod macros;
+
+fn context() -> Context {
+    MapBuilder::new()
+        .str("name", "<Ada>")
+        .list("items", vec![Context::Integer(1), Context::Integer(2)])
+        .build()
+}
+
+mstest!(
+    erb_style,
+    options = CompileOptions::new().delimiters("<%", "%>").unwrap(),
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    <%#items%><%.%>,<%/items%> <%name%> <%{name}%> <%&name%> {{name}}<%! note %>
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    1,2, &lt;Ada&gt; <Ada> <Ada> {{name}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn multi_character() {
+    let options = CompileOptions::new().delimiters(r"\begin{", "}").unwrap();
+    let text = r"\begin{#items}[\begin{.}]\begin{/items}";
+    assert_eq!(macros::render(text, options, context()), "[1][2]");
+}
+
+#[test]
+fn unicode() {
+    let options = CompileOptions::new().delimiters("«", "»").unwrap();
+    let text = "«#items»\n  «.»\n«/items»\n«{name}»";
+    assert_eq!(macros::render(text, options, context()), "  1\n  2\n<Ada>");
+    let options = CompileOptions::new().delimiters("⟦⟦", "⟧⟧").unwrap();
+    assert_eq!(
+        macros::render("é⟦⟦name⟧⟧é", options, context()),
+        "é&lt;Ada&gt;é"
+    );
+}
+
+mstest!(
+    set_delimiters,
+    options = CompileOptions::new().delimiters("<%", "%>").unwrap(),
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    <%name%> <%=[ ]=%>[name] <%name%> [={{ }}=]{{name}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    &lt;Ada&gt; &lt;Ada&gt; <%name%> &lt;Ada&gt;
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn partials() {
+    let options = CompileOptions::new().delimiters("<%", "%>").unwrap();
+    let partials = Partials::from([("item".to_string(), "(<%.%>)".to_string())]);
+    let text = "<%#items%><%>item%><%/items%>";
+    let rendered =
+        macros::render_backends(text, options, context(), &partials, &RenderOptions::new());
+    assert_eq!(rendered.unwrap(), "(1)(2)");
+}
+
+#[test]
+fn sigils() {
+    let sigils = Sigils {
+        section: '?',
+        close: '.',
+        comment: '#',
+        ..Sigils::default()
+    };
+    let options = CompileOptions::new().sigils(sigils).unwrap();
+    let text = "{{?items}}{{ . }}{{.items}}{{# note }}";
+    assert_eq!(macros::render(text, options.clone(), context()), "12");
+    let text = "{{#items}}";
+    assert_eq!(macros::render(text, options, context()), "");
+    let result = Template::compile("{{?items}}{{/items}}");
+    assert!(matches!(result, Err(Error::Parse { .. })));
+}
+
+fn sigils_options() -> CompileOptions {
+    let sigils = Sigils {
+        section: '§',
+        inverted: '¬',
+        ..Sigils::default()
+    };
+    let options = CompileOptions::new().delimiters("<<", ">>").unwrap();
+    options.sigils(sigils).unwrap()
+}
+
+mstest!(
+    sigils_and_delimiters,
+    options = sigils_options(),
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    <<§items>><<.>><</items>><<¬missing>>none<</missing>>
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    12none
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn invalid_delimiters() {
+    for (open, close) in [("", "}}"), ("{{", ""), ("<% ", "%>"), ("<=", "=>")] {
+        let error = CompileOptions::new().delimiters(open, close).unwrap_err();
+        assert!(matches!(error, Error::Syntax(_)), "{open} {close}");
+    }
+    let error = CompileOptions::new().delimiters("", "}}").unwrap_err();
+    assert_eq!(error.to_string(), "invalid delimiter \"\"");
+}
+
+#[test]
+fn invalid_sigils() {
+    let error = CompileOptions::new()
+        .sigils(Sigils {
+            section: '^',
+            ..Sigils::default()
+        })
+        .unwrap_err();
+    assert_eq!(error, Error::Syntax("sigil '^' is used twice".into()));
+    for c in [' ', '=', '{'] {
+        let sigils = Sigils {
+            comment: c,
+            ..Sigils::default()
+        };
+        let error = CompileOptions::new().sigils(sigils).unwrap_err();
+        assert_eq!(error, Error::Syntax(format!("invalid sigil {c:?}")));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use stache::ast::{Node, Span};
+use stache::{
+    CompileOptions, Context, ContextResolver, Error, Limit, MapBuilder, Output, Partials,
+    RenderOptions, Sigils, Template,
+};
+
+mod macros;
+
+#[derive(Debug, PartialEq)]
+struct Icon(String);
+
+/// A lookup with a fallback, as in `{{:name "anonymous"}}`.
+struct Fallback {
+    name: String,
+    default: String,
+}
+
+fn options() -> CompileOptions {
+    let options = CompileOptions::new()
+        .extension(
+            '@',
+            |content| match content {
+                "" => Err("expected an icon name".to_string()),
+                name => Ok(Icon(name.to_string())),
+            },
+            |icon: &Icon, _: &ContextResolver, out: &mut Output| {
+                out.write(&format!("<i class=\"icon-{}\"></i>", icon.0))
+            },
+        )
+        .unwrap();
+    options
+        .extension(
+            ':',
+            |content| {
+                let (name, default) = content.split_once(' ').unwrap_or((content, "\"\""));
+                let default = default.trim().trim_matches('"');
+                Ok(Fallback {
+                    name: name.to_string(),
+                    default: default.to_string(),
+                })
+            },
+            |fallback: &Fallback, context: &ContextResolver, out: &mut Output| match context
+                .get(&fallback.name)
+                .as_deref()
+            {
+                Some(Context::String(text)) => out.write_escaped(text),
+                Some(Context::Integer(number)) => out.write(&number.to_string()),
+                _ => out.write_escaped(&fallback.default),
+            },
+        )
+        .unwrap()
+}
+
+fn context() -> Context {
+    let user = HashMap::from([("name".to_string(), Context::String("<Ada>".into()))]);
+    MapBuilder::new()
+        .map("user", user)
+        .list("items", vec![Context::Integer(1), Context::Integer(2)])
+        .build()
+}
+
+#[test]
+fn parse() {
+    let template = Template::compile_with_options("a {{@ star }}", options()).unwrap();
+    let Node::Extension(extension) = &template.ast().children()[1] else {
+        panic!("expected an extension");
+    };
+    assert_eq!(extension.sigil, '@');
+    assert_eq!(extension.content, "star");
+    assert_eq!(extension.span, Span::new(2, 13));
+    assert_eq!(
+        extension.node.downcast_ref::<Icon>(),
+        Some(&Icon("star".into()))
+    );
+}
+
+mstest!(
+    render_hooks,
+    options = options(),
+    context(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{@star}} {{:user.name}} {{:user.age "unknown"}}
+    {{#items}}{{:.}}{{/items}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    <i class="icon-star"></i> &lt;Ada&gt; unknown
+    12
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+mstest!(
+    standalone,
+    options = options(),
+    context(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    <ul>
+      {{@list}}
+      {{#items}}
+      <li>{{.}}</li>
+      {{/items}}
+    </ul>
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    <ul>
+    <i class="icon-list"></i>  <li>1</li>
+      <li>2</li>
+    </ul>
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+#[test]
+fn partials() {
+    let partials = Partials::from([("icon".to_string(), "{{@star}}".to_string())]);
+    let render_options = RenderOptions::new();
+    let result = macros::render_backends(
+        "[{{>icon}}]",
+        options(),
+        context(),
+        &partials,
+        &render_options,
+    );
+    assert_eq!(result, Ok("[<i class=\"icon-star\"></i>]".into()));
+}
+
+#[test]
+fn limits() {
+    let limits = RenderOptions::new().max_output(10);
+    let partials = Partials::new();
+    let result = macros::render_backends("{{@star}}", options(), context(), &partials, &limits);
+    assert_eq!(result, Err(Error::Limit(Limit::Output)));
+}
+
+#[test]
+fn errors() {
+    let error = Template::compile_with_options("{{@}}", options())
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        Error::Extension {
+            sigil: '@',
+            message: "expected an icon name".into(),
+        }
+    );
+    assert_eq!(error.to_string(), "`@` tag failed: expected an icon name");
+}
+
+#[test]
+fn unregistered() {
+    let context = MapBuilder::new().str("@star", "variable").build();
+    let template = Template::compile("{{@star}}").unwrap();
+    assert_eq!(template.render(context).unwrap(), "variable");
+}
+
+#[test]
+fn invalid_sigils() {
+    let extension = |options: CompileOptions, sigil: char| {
+        options.extension(
+            sigil,
+            |_| Ok(()),
+            |_: &(), _: &ContextResolver, _: &mut Output| Ok(()),
+        )
+    };
+    let error = extension(CompileOptions::new(), '#').unwrap_err();
+    assert_eq!(error, Error::Syntax("sigil '#' is used twice".into()));
+    let registered = extension(CompileOptions::new(), '@').unwrap();
+    let error = extension(registered, '@').unwrap_err();
+    assert_eq!(error, Error::Syntax("sigil '@' is used twice".into()));
+    let error = extension(CompileOptions::new(), '{').unwrap_err();
+    assert_eq!(error, Error::Syntax("invalid sigil '{'".into()));
+    let error = options().sigils(Sigils {
+        comment: '@',
+        ..Sigils::default()
+    });
+    assert_eq!(
+        error.unwrap_err(),
+        Error::Syntax("sigil '@' is used twice".into())
+    );
+}
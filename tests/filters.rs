@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use stache::ast::{Filter, Node};
+use stache::{CompileOptions, Context, Error, MapBuilder, Partials, RenderOptions, Template};
+
+mod macros;
+
+fn options() -> CompileOptions {
+    CompileOptions::new().filters(true)
+}
+
+#[test]
+fn parse() {
+    let options = CompileOptions::new().filters(true);
+    let text = r#"{{ title | truncate 20 "…" | upper }}"#;
+    let template = Template::compile_with_options(text, options).unwrap();
+    let Node::Variable(variable) = &template.ast().children()[0] else {
+        panic!("expected a variable");
+    };
+    assert_eq!(variable.name, "title");
+    assert_eq!(
+        variable.filters,
+        vec![
+            Filter {
+                name: "truncate",
+                args: vec!["20", "…"],
+            },
+            Filter {
+                name: "upper",
+                args: vec![],
+            },
+        ]
+    );
+}
+
+mstest!(
+    quoted_pipe,
+    options = options(),
+    MapBuilder::new().str("missing", "").build(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{missing | default "a | b"}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    a | b
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+#[test]
+fn disabled_by_default() {
+    let template = Template::compile("{{a | upper}}").unwrap();
+    let Node::Variable(variable) = &template.ast().children()[0] else {
+        panic!("expected a variable");
+    };
+    assert_eq!(variable.name, "a | upper");
+    assert!(variable.filters.is_empty());
+}
+
+#[test]
+fn malformed() {
+    let options = CompileOptions::new().filters(true);
+    for text in ["{{a | }}", "{{a || upper}}", r#"{{a | default "b}}"#] {
+        let result = Template::compile_with_options(text, options.clone());
+        assert!(
+            matches!(result, Err(Error::Parse { .. })),
+            "{text} should not compile"
+        );
+    }
+}
+
+mstest!(
+    text_filters,
+    options = options(),
+    MapBuilder::new()
+        .str("name", "  Ada Lovelace ")
+        .str("title", "A very long title")
+        .build(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{name | trim | upper}}
+    {{name | trim | lower}}
+    {{title | truncate 6}}
+    {{title | truncate 6 !}}
+    {{title | truncate 60}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    ADA LOVELACE
+    ada lovelace
+    A very...
+    A very!
+    A very long title
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    default,
+    options = options(),
+    MapBuilder::new()
+        .str("empty", "")
+        .bool("no", false)
+        .int("zero", 0)
+        .build(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{missing | default none}}
+    {{empty | default none}}
+    {{no | default none}}
+    {{zero | default none}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    none
+    none
+    none
+    0
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    date,
+    options = options(),
+    MapBuilder::new()
+        .int("launch", 1_700_000_000)
+        .int("before", -1)
+        .build(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{launch | date}}
+    {{launch | date "%d/%m/%Y %H:%M:%S"}}
+    {{before | date}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    2023-11-14
+    14/11/2023 22:13:20
+    1969-12-31
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+fn json_context() -> Context {
+    let user = HashMap::from([
+        (String::from("name"), Context::String("Ada \"A\"".into())),
+        (String::from("age"), Context::Integer(36)),
+    ]);
+    MapBuilder::new()
+        .map("user", user)
+        .list("tags", vec![Context::Bool(true), Context::Null])
+        .build()
+}
+
+mstest!(
+    json,
+    options = options(),
+    json_context(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{{user | json}}}
+    {{{tags | json}}}
+    {{{missing | json}}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {"age":36,"name":"Ada \"A\""}
+    [true,null]
+    null
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+mstest!(
+    urlencode,
+    options = options(),
+    MapBuilder::new().str("query", "a b&c=é~").build(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{query | urlencode}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    a%20b%26c%3D%C3%A9~
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    pluralize,
+    options = options(),
+    MapBuilder::new()
+        .int("one", 1)
+        .int("two", 2)
+        .list("items", vec![Context::Null])
+        .build(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{one}} item{{one | pluralize}}
+    {{two}} item{{two | pluralize}}
+    {{two | pluralize es}}
+    {{items | pluralize child children}}
+    {{two | pluralize child children}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    1 item
+    2 items
+    es
+    child
+    children
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    escaped_after_filters,
+    options = options(),
+    MapBuilder::new().str("html", "<b>&amp;</b>").build(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{html | upper}}
+    {{{html | upper}}}
+    {{& html | truncate 3 ""}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    &lt;B&gt;&amp;AMP;&lt;/B&gt;
+    <B>&AMP;</B>
+    <b>
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+#[test]
+fn custom() {
+    let render_options = RenderOptions::new()
+        .filter("reverse", |value, _| match value {
+            Some(Context::String(text)) => Ok(Some(Context::String(text.chars().rev().collect()))),
+            _ => Err("expected a string".into()),
+        })
+        .filter("upper", |_, _| Ok(Some(Context::String("shadowed".into()))));
+    let context = || MapBuilder::new().str("name", "<Ada>").build();
+    let partials = Partials::new();
+    for (text, expected) in [
+        ("{{name | reverse}}", "&gt;adA&lt;"),
+        ("{{name | upper}}", "shadowed"),
+    ] {
+        let rendered =
+            macros::render_backends(text, options(), context(), &partials, &render_options);
+        assert_eq!(rendered.unwrap(), expected);
+    }
+}
+
+#[test]
+fn errors() {
+    let context = || MapBuilder::new().str("name", "Ada").build();
+    let error = macros::render_error("{{name | shout}}", options(), context());
+    assert_eq!(
+        error,
+        Error::Filter {
+            name: "shout".into(),
+            message: "no filter has this name".into(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "filter `shout` failed: no filter has this name"
+    );
+    let error = macros::render_error("{{name | truncate many}}", options(), context());
+    assert_eq!(
+        error,
+        Error::Filter {
+            name: "truncate".into(),
+            message: "expected a number of characters".into(),
+        }
+    );
+    let error = macros::render_error("{{name | date}}", options(), context());
+    assert!(matches!(error, Error::Filter { name, .. } if name == "date"));
+}
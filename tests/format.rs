@@ -0,0 +1,129 @@
+use std::fs;
+use std::process::Command;
+
+use stache::{format, format_with_options, Error, FormatOptions, Result, UnescapedStyle};
+
+#[test]
+fn tag_spacing() -> Result<()> {
+    let formatted = format("{{ name }} {{#a }}{{/ a}} {{> partial }}")?;
+    assert_eq!(formatted, "{{name}} {{#a}}{{/a}} {{>partial}}");
+
+    let options = FormatOptions::new().padding(true);
+    let formatted = format_with_options("{{name}} {{#a}}{{/a}}", options)?;
+    assert_eq!(formatted, "{{ name }} {{# a }}{{/ a }}");
+    Ok(())
+}
+
+#[test]
+fn unescaped_style() -> Result<()> {
+    let formatted = format("{{& a}} {{{ b }}}")?;
+    assert_eq!(formatted, "{{{a}}} {{{b}}}");
+
+    let options = FormatOptions::new().unescaped(UnescapedStyle::Ampersand);
+    let formatted = format_with_options("{{& a}} {{{ b }}}", options)?;
+    assert_eq!(formatted, "{{&a}} {{&b}}");
+    Ok(())
+}
+
+#[test]
+fn set_delimiters() -> Result<()> {
+    let formatted = format("{{=  <%   %>  =}}<% name %>")?;
+    assert_eq!(formatted, "{{=<% %>=}}<%name%>");
+    Ok(())
+}
+
+#[test]
+fn comments_untouched() -> Result<()> {
+    let formatted = format("{{!  keep   this  }}")?;
+    assert_eq!(formatted, "{{!  keep   this  }}");
+    Ok(())
+}
+
+#[test]
+fn reindent() -> Result<()> {
+    let text = "\
+{{#a}}
+{{#b}}
+  text
+      {{/b}}   
+{{! note }}
+{{/a}}
+";
+    let expected = "\
+{{#a}}
+  {{#b}}
+  text
+  {{/b}}
+  {{! note }}
+{{/a}}
+";
+    assert_eq!(format(text)?, expected);
+    Ok(())
+}
+
+#[test]
+fn partial_indentation_kept() -> Result<()> {
+    let text = "{{#a}}\n    {{>item}}\n{{/a}}\n";
+    assert_eq!(format(text)?, text);
+    Ok(())
+}
+
+#[test]
+fn inline_tags_kept() -> Result<()> {
+    let text = "  {{#a}} {{b}}\n  {{#c}}{{/c}}\n";
+    assert_eq!(format(text)?, text);
+    Ok(())
+}
+
+#[test]
+fn sigil_names_kept() -> Result<()> {
+    let formatted = format("{{ #hash }}")?;
+    assert_eq!(formatted, "{{ #hash}}");
+    Ok(())
+}
+
+#[test]
+fn unstable() {
+    // Writing `{{{a}}b}}}` with an ampersand would end the tag after `a`.
+    let options = FormatOptions::new().unescaped(UnescapedStyle::Ampersand);
+    assert_eq!(
+        format_with_options("{{{a}}b}}}", options),
+        Err(Error::Format)
+    );
+}
+
+#[test]
+fn idempotent() -> Result<()> {
+    let text = "{{# a }}\n    {{ b }}\n        {{^ c}}\n{{& d }}\n{{/c}}\n{{/a}}";
+    let once = format(text)?;
+    assert_eq!(format(&once)?, once);
+    Ok(())
+}
+
+#[test]
+fn cli_check() {
+    let dir = std::env::temp_dir().join(format!("stache-fmt-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let formatted = dir.join("formatted.mustache");
+    let unformatted = dir.join("unformatted.mustache");
+    fs::write(&formatted, "{{name}}\n").unwrap();
+    fs::write(&unformatted, "{{ name }}\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stache"))
+        .args(["fmt", "--check"])
+        .args([&formatted, &unformatted])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.trim(), unformatted.display().to_string());
+
+    let status = Command::new(env!("CARGO_BIN_EXE_stache"))
+        .args(["fmt"])
+        .arg(&unformatted)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(&unformatted).unwrap(), "{{name}}\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use stache::ast::{Argument, ArgumentValue, Name, Node};
+use stache::{
+    Block, CompileOptions, Context, Error, MapBuilder, Partials, RenderOptions, Schema, Template,
+};
+
+mod macros;
+
+fn options() -> CompileOptions {
+    CompileOptions::new().helpers(true)
+}
+
+fn map(entries: &[(&str, Context)]) -> HashMap<String, Context> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect()
+}
+
+fn people() -> Context {
+    let person = |name: &str, age: i64| MapBuilder::new().str("name", name).int("age", age).build();
+    MapBuilder::new()
+        .list(
+            "people",
+            vec![person("Eve", 30), person("Ada", 36), person("Bob", 4)],
+        )
+        .build()
+}
+
+#[test]
+fn parse() {
+    let options = CompileOptions::new().helpers(true);
+    let text = r#"{{#each_sorted a.b "two words" -1.5 key="name"}}{{/each_sorted}}"#;
+    let template = Template::compile_with_options(text, options).unwrap();
+    let Node::Section(section) = &template.ast().children()[0] else {
+        panic!("expected a section");
+    };
+    assert_eq!(section.name, "each_sorted");
+    assert_eq!(
+        section.args,
+        vec![
+            Argument {
+                key: None,
+                value: ArgumentValue::Name(Name::Path(vec!["a", "b"])),
+            },
+            Argument {
+                key: None,
+                value: ArgumentValue::String("two words"),
+            },
+            Argument {
+                key: None,
+                value: ArgumentValue::Number("-1.5"),
+            },
+            Argument {
+                key: Some("key"),
+                value: ArgumentValue::String("name"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn disabled_by_default() {
+    assert!(matches!(
+        Template::compile("{{#if_eq a b}}{{/if_eq}}"),
+        Err(Error::Parse { .. })
+    ));
+    let template = Template::compile("{{#a b}}x{{/a b}}").unwrap();
+    let Node::Section(section) = &template.ast().children()[0] else {
+        panic!("expected a section");
+    };
+    assert_eq!(section.name, "a b");
+    assert!(section.args.is_empty());
+}
+
+#[test]
+fn malformed() {
+    let options = CompileOptions::new().helpers(true);
+    for text in [
+        r#"{{#if_eq a "b}}{{/if_eq}}"#,
+        r#"{{#if_eq a "b"c}}{{/if_eq}}"#,
+        "{{#if_eq a =b}}{{/if_eq}}",
+        "{{#if_eq a key=}}{{/if_eq}}",
+        "{{^if_eq a b}}{{/if_eq}}",
+        "{{#if_eq a b}}{{/if_eq a b}}",
+    ] {
+        let result = Template::compile_with_options(text, options.clone());
+        assert!(
+            matches!(result, Err(Error::Parse { .. })),
+            "{text} should not compile"
+        );
+    }
+    let result = Template::compile_with_options("{{#with a..b}}{{/with}}", options);
+    assert!(matches!(result, Err(Error::Name { .. })));
+}
+
+mstest!(
+    if_eq,
+    options = options(),
+    MapBuilder::new()
+        .str("status", "paid")
+        .int("count", 3)
+        .build(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#if_eq status "paid"}}Paid{{/if_eq}}{{#if_eq status "due"}}Due{{/if_eq}}
+    [{{#if_eq count 3}}three{{/if_eq}}]
+    [{{#if_eq count status}}{{/if_eq}}]
+    [{{#if_eq missing 3}}{{/if_eq}}]
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Paid
+    [three]
+    []
+    []
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+#[test]
+fn each_sorted() {
+    let text = "{{#each_sorted people key=\"name\"}}{{name}} {{/each_sorted}}";
+    assert_eq!(macros::render(text, options(), people()), "Ada Bob Eve ");
+    let text = "{{#each_sorted people key=\"age\"}}{{age}} {{/each_sorted}}";
+    assert_eq!(macros::render(text, options(), people()), "4 30 36 ");
+    let context = || {
+        let words = ["b", "c", "a"].map(|word| Context::String(word.into()));
+        MapBuilder::new().list("words", words.to_vec()).build()
+    };
+    let text = "{{#each_sorted words}}{{.}}{{/each_sorted}}";
+    assert_eq!(macros::render(text, options(), context()), "abc");
+}
+
+#[test]
+fn each_sorted_mixed() {
+    let context = || {
+        let item = |name: &str, value: Option<Context>| {
+            let name = ("name", Context::String(name.into()));
+            let entries = match value {
+                Some(value) => map(&[name, ("value", value)]),
+                None => map(&[name]),
+            };
+            Context::Map(entries)
+        };
+        let items = vec![
+            item("b", Some(Context::String("b".into()))),
+            item("2", Some(Context::Integer(2))),
+            item("list", Some(Context::List(vec![]))),
+            item("nan", Some(Context::Float(f64::NAN))),
+            item("null", Some(Context::Null)),
+            item("true", Some(Context::Bool(true))),
+            item("a", Some(Context::String("a".into()))),
+            item("-1.5", Some(Context::Float(-1.5))),
+            item("map", Some(Context::Map(Default::default()))),
+            item("missing", None),
+        ];
+        MapBuilder::new().list("items", items).build()
+    };
+    let text = "{{#each_sorted items key=\"value\"}}{{name}} {{/each_sorted}}";
+    assert_eq!(
+        macros::render(text, options(), context()),
+        "null missing -1.5 2 nan a b true list map "
+    );
+}
+
+#[test]
+fn with() {
+    let context = || {
+        let user = map(&[("name", Context::String("Ada".into()))]);
+        MapBuilder::new()
+            .map("user", user)
+            .str("site", "example.com")
+            .bool("missing", false)
+            .build()
+    };
+    let text = "{{#with user}}{{name}}@{{site}}{{/with}}{{#with missing}}no{{/with}}";
+    assert_eq!(
+        macros::render(text, options(), context()),
+        "Ada@example.com"
+    );
+}
+
+mstest!(
+    nested,
+    options = options(),
+    people(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#each_sorted people key="name"}}{{#if_eq name "Bob"}}{{#with .}}[{{age}}]{{/with}}{{/if_eq}}{{name}} {{/each_sorted}}|
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Ada [4]Bob Eve |
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+#[test]
+fn custom() {
+    let render_options = RenderOptions::new()
+        .helper("repeat", |block: &mut Block| {
+            let Some(Context::Integer(times)) = block.arg(0) else {
+                return Err(block.error("expected a number"));
+            };
+            for i in 0..*times {
+                if i > 0 {
+                    block.write(", ")?;
+                }
+                block.render_with(&MapBuilder::new().int("i", i).build())?;
+            }
+            Ok(())
+        })
+        .helper("with", |block: &mut Block| block.write(block.name()));
+    let context = || MapBuilder::new().str("name", "<Ada>").build();
+    let text = "{{#repeat 3}}{{i}}:{{name}}{{/repeat}}";
+    let partials = Partials::new();
+    let rendered = macros::render_backends(text, options(), context(), &partials, &render_options);
+    assert_eq!(
+        rendered.unwrap(),
+        "0:&lt;Ada&gt;, 1:&lt;Ada&gt;, 2:&lt;Ada&gt;"
+    );
+    let text = "{{#with name}}{{/with}}";
+    let rendered = macros::render_backends(text, options(), context(), &partials, &render_options);
+    assert_eq!(rendered.unwrap(), "with");
+}
+
+#[test]
+fn errors() {
+    let error = macros::render_error("{{#shout a}}{{/shout}}", options(), people());
+    assert_eq!(
+        error,
+        Error::Helper {
+            name: "shout".into(),
+            message: "no helper has this name".into(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "helper `shout` failed: no helper has this name"
+    );
+    let error = macros::render_error("{{#if_eq a}}{{/if_eq}}", options(), people());
+    assert_eq!(
+        error,
+        Error::Helper {
+            name: "if_eq".into(),
+            message: "expected two values".into(),
+        }
+    );
+    let text = "{{#each_sorted \"people\"}}{{/each_sorted}}";
+    let error = macros::render_error(text, options(), people());
+    assert_eq!(
+        error,
+        Error::Helper {
+            name: "each_sorted".into(),
+            message: "expected a list".into(),
+        }
+    );
+}
+
+#[test]
+fn schema() {
+    let options = CompileOptions::new().helpers(true);
+    let text = "{{#with user}}{{status}}{{/with}}{{#if_eq status \"paid\"}}{{/if_eq}}";
+    let template = Template::compile_with_options(text, options).unwrap();
+    let schema = Schema::from_context(&MapBuilder::new().str("status", "paid").build());
+    let issues = template.check(&schema).unwrap();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].name, "user");
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use stache::{CompileOptions, Context, MapBuilder, Schema, Template};
+
+mod macros;
+
+fn context() -> Context {
+    let person = |name: &str| MapBuilder::new().str("name", name).build();
+    let grid = Context::List(vec![
+        Context::List(vec![Context::Integer(1), Context::Integer(2)]),
+        Context::List(vec![Context::Integer(3)]),
+    ]);
+    MapBuilder::new()
+        .list("people", vec![person("Ada"), person("Bob"), person("Eve")])
+        .list("empty", Vec::new())
+        .list("grid", vec![grid.clone()])
+        .str("title", "Straße")
+        .map(
+            "stats",
+            HashMap::from([("length".to_string(), Context::String("long".into()))]),
+        )
+        .build()
+}
+
+mstest!(
+    indexes,
+    options = CompileOptions::new().list_indexing(true),
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{people.0.name}} {{people.2.name}}
+    {{people.-1.name}} {{people.-3.name}}
+    [{{people.3.name}}{{people.-4.name}}{{people.x}}]
+    {{grid.0.0.1}}{{grid.0.1.0}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Ada Eve
+    Eve Ada
+    []
+    23
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    length,
+    options = CompileOptions::new().list_indexing(true),
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{people.length}} {{empty.length}} {{title.length}} {{stats.length}}
+    [{{people.length.value}}{{people.0.length}}]
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    3 0 6 long
+    []
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+// `{{#empty.length}}` renders because zero is truthy under the spec rules.
+mstest!(
+    sections,
+    options = CompileOptions::new().list_indexing(true),
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#people.1}}{{name}}{{/people.1}} {{#people.length}}{{.}} people{{/people.length}}
+    {{^empty.0}}none{{/empty.0}} {{#empty.length}}{{.}}{{/empty.length}}
+    {{#people.length}}{{#people.0}}{{name}}/{{people.length}}{{/people.0}}{{/people.length}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Bob 3 people
+    none 0
+    Ada/3
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    disabled_by_default,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    [{{people.0.name}}{{people.length}}{{title.length}}]
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    []
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn schema() {
+    let options = CompileOptions::new().list_indexing(true);
+    let text = "{{people.0.name}}{{people.length}}{{title.length}}{{people.0.age}}";
+    let template = Template::compile_with_options(text, options).unwrap();
+    let issues = template.check(&Schema::from_context(&context())).unwrap();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].name, "people.0.age");
+    let template = Template::compile("{{people.0.name}}").unwrap();
+    assert_eq!(
+        template
+            .check(&Schema::from_context(&context()))
+            .unwrap()
+            .len(),
+        1
+    );
+}
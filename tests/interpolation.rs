@@ -622,3 +622,24 @@ mstest!(
     ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
     "
 );
+
+mstest!(
+    maps_and_lists,
+    Context::Map(HashMap::from([
+        (
+            String::from("map"),
+            Context::Map(HashMap::from([(String::from("a"), Context::Integer(1))]))
+        ),
+        (
+            String::from("list"),
+            Context::List(vec![Context::Integer(1), Context::Integer(2)])
+        ),
+    ])),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    |{{map}}|{{{list}}}|{{&map}}|{{#list}}{{.}}{{/list}}|
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    ||||12|
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
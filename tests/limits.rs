@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use stache::cst::SyntaxTree;
+use stache::{CompileOptions, Context, Error, Limit, RenderOptions, Template, VecBuilder};
+
+fn items(count: i64) -> Context {
+    let list = (0..count).fold(VecBuilder::new(), |list, i| list.int(i));
+    Context::Map(HashMap::from([(String::from("items"), list.build())]))
+}
+
+#[test]
+fn max_size() {
+    let options = CompileOptions::new().max_size(8);
+    let template = Template::compile_with_options("{{greeting}} world", options);
+    assert!(matches!(template, Err(Error::Limit(Limit::Size))));
+}
+
+#[test]
+fn max_nesting() {
+    let options = CompileOptions::new().max_nesting(2);
+    let text = "{{#a}}{{#b}}{{#c}}{{/c}}{{/b}}{{/a}}";
+    let template = Template::compile_with_options(text, options);
+    assert!(matches!(template, Err(Error::Limit(Limit::Nesting))));
+
+    let options = CompileOptions::new().max_nesting(3);
+    assert!(Template::compile_with_options(text, options).is_ok());
+}
+
+#[test]
+fn max_nesting_default() {
+    let text = "{{#a}}".repeat(100_000);
+    let template = Template::compile(&text);
+    assert!(matches!(template, Err(Error::Limit(Limit::Nesting))));
+    let tree = SyntaxTree::parse(&text);
+    assert!(matches!(tree, Err(Error::Limit(Limit::Nesting))));
+    let text = "{{#a}}".repeat(256) + &"{{/a}}".repeat(256);
+    assert!(Template::compile(&text).is_ok());
+    let text = "{{#a}}".repeat(257) + &"{{/a}}".repeat(257);
+    let template = Template::compile(&text);
+    assert!(matches!(template, Err(Error::Limit(Limit::Nesting))));
+    let tree = SyntaxTree::parse(&text);
+    assert!(matches!(tree, Err(Error::Limit(Limit::Nesting))));
+}
+
+#[test]
+fn max_nesting_partials() {
+    let options = CompileOptions::new().max_nesting(1);
+    let template = Template::compile_with_options("{{>partial}}", options).unwrap();
+    let partials = HashMap::from([(
+        String::from("partial"),
+        String::from("{{#a}}{{#b}}{{/b}}{{/a}}"),
+    )]);
+    let rendered = template.render_with_partials(Context::Null, partials);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Nesting)));
+}
+
+#[test]
+fn max_output() {
+    let template = Template::compile("{{#items}}{{.}}{{/items}}").unwrap();
+    let options = RenderOptions::new().max_output(5);
+    let rendered = template.render_with_options(items(5), HashMap::new(), options);
+    assert_eq!(rendered, Ok(String::from("01234")));
+
+    let options = RenderOptions::new().max_output(4);
+    let rendered = template.render_with_options(items(5), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Output)));
+}
+
+#[test]
+fn max_iterations() {
+    let template = Template::compile("{{#items}}{{.}}{{/items}}").unwrap();
+    let options = RenderOptions::new().max_iterations(3);
+    let rendered = template.render_with_options(items(4), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Iterations)));
+}
+
+#[test]
+fn max_partials() {
+    let template = Template::compile("{{#items}}{{>item}}{{/items}}").unwrap();
+    let partials = HashMap::from([(String::from("item"), String::from("{{.}}"))]);
+    let options = RenderOptions::new().max_partials(2);
+    let rendered = template.render_with_options(items(3), partials, options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Partials)));
+}
+
+#[test]
+fn deadline() {
+    let template = Template::compile("{{#items}}{{.}}{{/items}}").unwrap();
+    let options = RenderOptions::new().deadline(Instant::now() - Duration::from_secs(1));
+    let rendered = template.render_with_options(items(3), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Deadline)));
+}
+
+#[test]
+fn cancel() {
+    let template = Template::compile("{{#items}}{{.}}{{/items}}").unwrap();
+    let flag = Arc::new(AtomicBool::new(true));
+    let options = RenderOptions::new().cancel(flag);
+    let rendered = template.render_with_options(items(3), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Cancelled)));
+}
+
+#[test]
+fn deadline_without_sections() {
+    let text = "{{a}} and {{b}}\n".repeat(10_000);
+    let template = Template::compile(&text).unwrap();
+    let context = Context::Map(HashMap::from([
+        (String::from("a"), Context::String("x".repeat(100))),
+        (String::from("b"), Context::Integer(1)),
+    ]));
+    let options = RenderOptions::new().deadline(Instant::now() - Duration::from_secs(1));
+    let rendered = template.render_with_options(context.clone(), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Deadline)));
+
+    let flag = Arc::new(AtomicBool::new(true));
+    let options = RenderOptions::new().cancel(flag);
+    let rendered = template.render_with_options(context.clone(), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Cancelled)));
+
+    let rendered = template.render_with_options(context, HashMap::new(), RenderOptions::new());
+    assert!(rendered.is_ok());
+}
+
+#[test]
+fn deadline_long_write() {
+    let template = Template::compile("{{text}}").unwrap();
+    let text = Context::String("<".repeat(1 << 20));
+    let context = Context::Map(HashMap::from([(String::from("text"), text)]));
+    let options = RenderOptions::new().deadline(Instant::now() - Duration::from_secs(1));
+    let rendered = template.render_with_options(context, HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Deadline)));
+}
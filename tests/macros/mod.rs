@@ -1,6 +1,55 @@
+use stache::{Backend, CompileOptions, Context, Error, Partials, RenderOptions, Template};
+
+/// Compiles and renders a template with every backend, checks that they agree
+/// and returns what they rendered. Compile errors are returned like render
+/// errors.
+#[allow(dead_code)]
+pub fn render_backends(
+    text: &str,
+    options: CompileOptions,
+    context: Context,
+    partials: &Partials,
+    render_options: &RenderOptions,
+) -> Result<String, Error> {
+    let [tree, program] = [Backend::Tree, Backend::Program].map(|backend| {
+        let template = Template::compile_with_options(text, options.clone().backend(backend))?;
+        template.render_with_options(context.clone(), partials.clone(), render_options.clone())
+    });
+    assert_eq!(tree, program, "the backends disagree on {text:?}");
+    tree
+}
+
+/// Renders a template with every backend and no partials, panicking if it
+/// does not compile or render.
+#[allow(dead_code)]
+pub fn render(text: &str, options: CompileOptions, context: Context) -> String {
+    try_render(text, options, context).unwrap()
+}
+
+/// Renders a template with every backend and no partials.
+#[allow(dead_code)]
+pub fn try_render(text: &str, options: CompileOptions, context: Context) -> Result<String, Error> {
+    render_backends(
+        text,
+        options,
+        context,
+        &Partials::new(),
+        &RenderOptions::new(),
+    )
+}
+
+/// Renders a template that is expected to fail with every backend and returns
+/// the error.
+#[allow(dead_code)]
+pub fn render_error(text: &str, options: CompileOptions, context: Context) -> Error {
+    try_render(text, options, context).unwrap_err()
+}
+
 /// Generates a test that compares the parsed Mustache input against the
 /// expected text output. Use the following separator to frame the input and
-/// expected output: `~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~`.
+/// expected output: `~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~`. The template
+/// is rendered with every backend, with the compile options given after
+/// `options =` if there are any.
 ///
 /// # Examples
 ///
@@ -20,28 +69,57 @@
 ///     ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 ///     "
 /// );
+///
+/// mstest!(
+///     shout,
+///     options = CompileOptions::new().filters(true),
+///     Context::Map(
+///         HashMap::from(
+///             [(String::from("greeting", Context::String("hello".into())))]
+///         )
+///     ),
+///     "
+///     ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+///     {{greeting | upper}} world!
+///     ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+///     HELLO world!
+///     ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+///     "
+/// );
 /// ```
 #[macro_export]
 macro_rules! mstest {
-    ($name:ident, $context:expr, $test:expr) => {
+    ($name:ident, options = $options:expr, $context:expr, $test:expr) => {
         #[test]
         fn $name() {
             use indoc::indoc;
-            use stache::{Context, Template};
+            #[allow(unused_imports)]
+            use stache::{Backend, Context, Template};
 
             let separator = "~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~";
             let components: Vec<&str> = indoc!($test).split(separator).collect();
             let input = &components[1][1..]; // skip leading newline
-            let template = Template::compile(input).unwrap();
-            let rendered = template.render($context);
             let expected = &components[2][1..]; // skip leading newline
-            if rendered != expected {
-                panic!(
-                    "\nFailed to parse template.\n{separator}Template\n{input}{separator}Expected\n{expected}{separator}Actual\n{rendered}"
-                );
+            for backend in [Backend::Tree, Backend::Program] {
+                let options = $options.backend(backend);
+                let template = Template::compile_with_options(input, options).unwrap();
+                let rendered = template.render($context).unwrap();
+                if rendered != expected {
+                    panic!(
+                        "\nFailed to parse template ({backend:?} backend).\n{separator}Template\n{input}{separator}Expected\n{expected}{separator}Actual\n{rendered}"
+                    );
+                }
             }
         }
     };
+    ($name:ident, $context:expr, $test:expr) => {
+        mstest!(
+            $name,
+            options = stache::CompileOptions::new(),
+            $context,
+            $test
+        );
+    };
 }
 
 /// Generates a test that compares the parsed Mustache input against the
@@ -77,18 +155,21 @@ macro_rules! mstest_with_partials {
         #[test]
         fn $name() {
             use indoc::indoc;
-            use stache::{Context, Template};
+            use stache::{Backend, CompileOptions, Context, Template};
 
             let separator = "~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~";
             let components: Vec<&str> = indoc!($test).split(separator).collect();
             let input = &components[1][1..]; // skip leading newline
-            let template = Template::compile(input).unwrap();
-            let rendered = template.render_with_partials($context, $partials);
             let expected = &components[2][1..]; // skip leading newline
-            if rendered != expected {
-                panic!(
-                    "\nFailed to parse template.\n{separator}Template\n{input}{separator}Expected\n{expected}{separator}Actual\n{rendered}"
-                );
+            for backend in [Backend::Tree, Backend::Program] {
+                let options = CompileOptions::new().backend(backend);
+                let template = Template::compile_with_options(input, options).unwrap();
+                let rendered = template.render_with_partials($context, $partials).unwrap();
+                if rendered != expected {
+                    panic!(
+                        "\nFailed to parse template ({backend:?} backend).\n{separator}Template\n{input}{separator}Expected\n{expected}{separator}Actual\n{rendered}"
+                    );
+                }
             }
         }
     };
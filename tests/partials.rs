@@ -1,6 +1,9 @@
-mod macros;
 use std::collections::HashMap;
 
+use stache::{Context, Error, RenderOptions, Template};
+
+mod macros;
+
 mstest_with_partials!(
     basic_behavior,
     Context::Map(HashMap::from([])),
@@ -170,3 +173,37 @@ mstest_with_partials!(
     ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
     "
 );
+
+#[test]
+fn recursion_limit() {
+    let template = Template::compile("{{>page}}").unwrap();
+    let partials = HashMap::from([
+        (String::from("page"), String::from("{{>list}}")),
+        (String::from("list"), String::from("{{>node}}")),
+        (String::from("node"), String::from("{{>node}}")),
+    ]);
+    let options = RenderOptions::new().max_depth(4);
+    let rendered = template.render_with_options(Context::Null, partials, options);
+    let chain = ["page", "list", "node", "node", "node"]
+        .map(String::from)
+        .to_vec();
+    assert_eq!(rendered, Err(Error::Depth(chain)));
+}
+
+#[test]
+fn recursion_limit_default() {
+    let template = Template::compile("{{>node}}").unwrap();
+    let partials = HashMap::from([(String::from("node"), String::from("{{>node}}"))]);
+    let rendered = template.render_with_partials(Context::Null, partials);
+    assert!(matches!(rendered, Err(Error::Depth(_))));
+}
+
+#[test]
+fn recursion_limit_sections() {
+    let template = Template::compile("{{#a}}{{#a}}{{#a}}x{{/a}}{{/a}}{{/a}}").unwrap();
+    let context = Context::Map(HashMap::from([(String::from("a"), Context::Bool(true))]));
+    let options = RenderOptions::new().max_depth(2);
+    let rendered = template.render_with_options(context, HashMap::new(), options);
+    let chain = ["#a", "#a", "#a"].map(String::from).to_vec();
+    assert_eq!(rendered, Err(Error::Depth(chain)));
+}
@@ -0,0 +1,272 @@
+use stache::ast::{Node, Pragma, Span};
+use stache::{
+    format, CompileOptions, Context, Error, MapBuilder, Partials, RenderOptions, Template,
+};
+
+mod macros;
+
+fn context() -> Context {
+    MapBuilder::new()
+        .str("name", "Ada")
+        .int("zero", 0)
+        .map("empty", Default::default())
+        .list(
+            "items",
+            vec![
+                MapBuilder::new().int("id", 1).build(),
+                MapBuilder::new().int("id", 2).str("name", "Bob").build(),
+            ],
+        )
+        .build()
+}
+
+#[test]
+fn parse() {
+    let text = "{{! settings }}\n{{%STRICT sections=true}}\n{{%FILTERS}}\n{{name}}";
+    let template = Template::compile(text).unwrap();
+    let Node::Root(root) = template.ast() else {
+        panic!("expected a root");
+    };
+    assert_eq!(
+        root.pragmas,
+        vec![
+            Pragma {
+                name: "STRICT",
+                options: vec![("sections", "true")],
+                span: Span::new(16, 41),
+            },
+            Pragma {
+                name: "FILTERS",
+                options: vec![],
+                span: Span::new(42, 54),
+            },
+        ]
+    );
+    assert_eq!(template.render(context()).unwrap(), "Ada");
+}
+
+#[test]
+fn strict() {
+    let options = CompileOptions::new();
+    for (text, expected) in [
+        ("{{%STRICT}}\nHello {{name}}", "Hello Ada"),
+        ("{{%STRICT}}{{#user}}{{/user}}", ""),
+        ("{{%STRICT enabled=false}}{{user}}", ""),
+    ] {
+        assert_eq!(macros::render(text, options.clone(), context()), expected);
+    }
+    let text = "{{%STRICT}}\n{{name}} {{user.name}}";
+    let error = macros::render_error(text, options, context());
+    assert_eq!(error, Error::Missing("user.name".into()));
+    assert_eq!(error.to_string(), "`user.name` is not defined");
+}
+
+#[test]
+fn strict_sections() {
+    let text = "{{%STRICT sections=true}}{{^user}}guest{{/user}}";
+    let error = macros::render_error(text, CompileOptions::new(), context());
+    assert_eq!(error, Error::Missing("user".into()));
+    let text = "{{%STRICT sections=true}}{{#name}}{{.}}{{/name}}";
+    assert_eq!(
+        macros::render(text, CompileOptions::new(), context()),
+        "Ada"
+    );
+}
+
+#[test]
+fn switches() {
+    for (text, options, expected) in [
+        (
+            "{{%FILTERS}}\n{{name | upper}}",
+            CompileOptions::new(),
+            "ADA",
+        ),
+        (
+            "{{%FILTERS enabled=false}}\n{{name | upper}}",
+            CompileOptions::new().filters(true),
+            "",
+        ),
+        (
+            "{{%HELPERS}}\n{{#if_eq name \"Ada\"}}yes{{/if_eq}}",
+            CompileOptions::new(),
+            "yes",
+        ),
+        (
+            "{{%WHITESPACE-CONTROL}}\n[ {{~name~}} ]",
+            CompileOptions::new(),
+            "[Ada]",
+        ),
+        (
+            "{{%WHITESPACE-CONTROL enabled=false}}\n[ {{~name~}} ]",
+            CompileOptions::new().whitespace_control(true),
+            "[  ]",
+        ),
+    ] {
+        assert_eq!(macros::render(text, options, context()), expected);
+    }
+}
+
+#[test]
+fn truthiness() {
+    for (text, expected) in [
+        (
+            "{{#zero}}zero{{/zero}}{{#empty}}empty{{/empty}}",
+            "zeroempty",
+        ),
+        (
+            "{{%TRUTHINESS rules=javascript}}\n{{#zero}}zero{{/zero}}{{#empty}}empty{{/empty}}",
+            "empty",
+        ),
+        (
+            "{{%TRUTHINESS rules=python}}\n{{#zero}}zero{{/zero}}{{^empty}}none{{/empty}}",
+            "none",
+        ),
+    ] {
+        assert_eq!(
+            macros::render(text, CompileOptions::new(), context()),
+            expected
+        );
+    }
+    let partials = Partials::from([(
+        "spec".to_string(),
+        "{{%TRUTHINESS rules=spec}}{{#zero}}zero{{/zero}}".to_string(),
+    )]);
+    let text = "{{%TRUTHINESS rules=javascript}}{{#zero}}js{{/zero}}{{>spec}}";
+    let (options, render_options) = (CompileOptions::new(), RenderOptions::new());
+    let result = macros::render_backends(text, options, context(), &partials, &render_options);
+    assert_eq!(result, Ok("zero".into()));
+}
+
+mstest!(
+    anchored_dot,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{%ANCHORED-DOT}}
+    {{#items}}[{{name}}|{{.name}}{{#.name}}!{{/.name}}]{{/items}}
+    {{.name}} {{#items}}{{.id}}{{/items}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    [Ada|][Bob|Bob!]
+    Ada 12
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn anchored_dot_disabled() {
+    assert!(matches!(
+        Template::compile("{{#items}}{{.name}}{{/items}}"),
+        Err(Error::Name { .. })
+    ));
+    let text = "{{%ANCHORED-DOT enabled=false}}\n{{.name}}";
+    assert!(matches!(Template::compile(text), Err(Error::Name { .. })));
+}
+
+#[test]
+fn partials() {
+    let partials = Partials::from([
+        ("strict".to_string(), "{{%STRICT}}{{user}}".to_string()),
+        ("lenient".to_string(), "{{user}}".to_string()),
+    ]);
+    let options = CompileOptions::new();
+    let result = macros::render_backends(
+        "{{user}}{{>strict}}",
+        options.clone(),
+        context(),
+        &partials,
+        &RenderOptions::new(),
+    );
+    assert_eq!(result, Err(Error::Missing("user".into())));
+    let result = macros::render_backends(
+        "{{%STRICT}}{{name}}{{>lenient}}",
+        options,
+        context(),
+        &partials,
+        &RenderOptions::new(),
+    );
+    assert_eq!(result, Ok("Ada".into()));
+}
+
+#[test]
+fn partial_errors() {
+    let partials = Partials::from([
+        ("unknown".to_string(), "{{%ESCAPE}}".to_string()),
+        ("unclosed".to_string(), "{{#name}}".to_string()),
+    ]);
+    let options = CompileOptions::new();
+    let result = macros::render_backends(
+        "{{>unknown}}",
+        options.clone(),
+        context(),
+        &partials,
+        &RenderOptions::new(),
+    );
+    assert!(matches!(result, Err(Error::Pragma { .. })), "{result:?}");
+    let result = macros::render_backends(
+        "[{{>unclosed}}]",
+        options,
+        context(),
+        &partials,
+        &RenderOptions::new(),
+    );
+    assert_eq!(result, Ok("[]".into()));
+}
+
+#[test]
+fn invalid() {
+    let error = Template::compile("{{%ESCAPE}}").err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "invalid pragma `ESCAPE` at 1:1: unknown pragma"
+    );
+    for (text, message) in [
+        ("\n{{%STRICT variables=true}}", "unknown option `variables`"),
+        (
+            "\n{{%STRICT sections=yes}}",
+            "expected `sections` to be `true` or `false`",
+        ),
+        (
+            "\n{{%TRUTHINESS rules=perl}}",
+            "expected `rules` to be `spec`, `javascript` or `python`",
+        ),
+    ] {
+        let Err(Error::Pragma {
+            name,
+            message: actual,
+            position,
+            ..
+        }) = Template::compile(text)
+        else {
+            panic!("{text} should not compile");
+        };
+        assert_eq!(actual, message);
+        assert!(text.contains(&name));
+        assert_eq!((position.line, position.column), (2, 1));
+    }
+}
+
+#[test]
+fn malformed() {
+    for text in [
+        "{{%}}",
+        "{{%STRICT sections}}",
+        "{{%STRICT =true}}",
+        "Hello\n{{%STRICT}}",
+        "{{name}}{{%STRICT}}",
+        "{{#name}}{{%STRICT}}{{/name}}",
+    ] {
+        assert!(
+            matches!(Template::compile(text), Err(Error::Parse { .. })),
+            "{text} should not compile"
+        );
+    }
+}
+
+#[test]
+fn formatting() {
+    let text = "{{%  STRICT sections=true }}\n{{ name }}\n";
+    assert_eq!(
+        format(text).unwrap(),
+        "{{%STRICT sections=true}}\n{{name}}\n"
+    );
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use stache::ast::{Name, Variable};
+use stache::visit::VisitorMut;
+use stache::{
+    Backend, CompileOptions, Context, Error, Limit, MapBuilder, RenderOptions, Template, VecBuilder,
+};
+
+fn compile(text: &str) -> Template<'_> {
+    let options = CompileOptions::new().backend(Backend::Program);
+    Template::compile_with_options(text, options).unwrap()
+}
+
+fn items(count: i64) -> Context {
+    let list = (0..count).fold(VecBuilder::new(), |list, i| list.int(i));
+    Context::Map(HashMap::from([(String::from("items"), list.build())]))
+}
+
+#[test]
+fn matches_tree() {
+    let text = "\
+{{#people}}
+  {{name}} <{{email}}>{{^admin}} (guest){{/admin}}
+  {{#tags}}[{{.}}]{{/tags}}{{^tags}}no tags{{/tags}}
+  {{#address}}{{city}}, {{country}}{{/address}}
+{{/people}}
+{{^people}}nobody{{/people}}
+";
+    let person = |name: &str, admin: bool, tags: &[&str]| {
+        let tags = tags.iter().map(|tag| Context::String(tag.to_string()));
+        let address = HashMap::from([
+            (String::from("city"), Context::String("Oslo".into())),
+            (String::from("country"), Context::String("Norway".into())),
+        ]);
+        MapBuilder::new()
+            .str("name", name)
+            .str("email", &format!("{name}@example.com"))
+            .bool("admin", admin)
+            .list("tags", tags.collect())
+            .map("address", address)
+            .build()
+    };
+    let context = || {
+        MapBuilder::new()
+            .list(
+                "people",
+                vec![
+                    person("Ada & Bob", true, &["a", "<b>"]),
+                    person("Eve", false, &[]),
+                ],
+            )
+            .str("country", "unused")
+            .build()
+    };
+    let tree = Template::compile(text).unwrap().render(context()).unwrap();
+    let program = compile(text).render(context()).unwrap();
+    assert_eq!(program, tree);
+}
+
+#[test]
+fn max_output() {
+    let template = compile("{{#items}}{{.}}{{/items}}");
+    let options = RenderOptions::new().max_output(5);
+    let rendered = template.render_with_options(items(5), HashMap::new(), options);
+    assert_eq!(rendered, Ok(String::from("01234")));
+
+    let template = compile("{{text}}");
+    let context = MapBuilder::new().str("text", "<>").build();
+    let options = RenderOptions::new().max_output(7);
+    let rendered = template.render_with_options(context, HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Output)));
+}
+
+#[test]
+fn max_iterations() {
+    let template = compile("{{#items}}{{.}}{{/items}}");
+    let options = RenderOptions::new().max_iterations(4);
+    let rendered = template.render_with_options(items(4), HashMap::new(), options);
+    assert_eq!(rendered, Ok(String::from("0123")));
+
+    let options = RenderOptions::new().max_iterations(3);
+    let rendered = template.render_with_options(items(4), HashMap::new(), options);
+    assert_eq!(rendered, Err(Error::Limit(Limit::Iterations)));
+}
+
+#[test]
+fn recursion_limit() {
+    let template = compile("{{>page}}");
+    let partials = HashMap::from([
+        (String::from("page"), String::from("{{>list}}")),
+        (String::from("list"), String::from("{{>node}}")),
+        (String::from("node"), String::from("{{>node}}")),
+    ]);
+    let options = RenderOptions::new().max_depth(4);
+    let rendered = template.render_with_options(Context::Null, partials, options);
+    let chain = ["page", "list", "node", "node", "node"]
+        .map(String::from)
+        .to_vec();
+    assert_eq!(rendered, Err(Error::Depth(chain)));
+}
+
+#[test]
+fn recursion_limit_sections() {
+    let template = compile("{{#a}}{{#a}}{{#a}}x{{/a}}{{/a}}{{/a}}");
+    let context = || Context::Map(HashMap::from([(String::from("a"), Context::Bool(true))]));
+    let options = RenderOptions::new().max_depth(3);
+    let rendered = template.render_with_options(context(), HashMap::new(), options);
+    assert_eq!(rendered, Ok(String::from("x")));
+
+    let options = RenderOptions::new().max_depth(2);
+    let rendered = template.render_with_options(context(), HashMap::new(), options);
+    let chain = ["#a", "#a", "#a"].map(String::from).to_vec();
+    assert_eq!(rendered, Err(Error::Depth(chain)));
+}
+
+struct Rename;
+
+impl<'t> VisitorMut<'t> for Rename {
+    fn visit_variable_mut(&mut self, variable: &mut Variable<'t>) {
+        variable.name = Name::Key("nickname");
+    }
+}
+
+#[test]
+fn ast_mut() {
+    let mut template = compile("Hello {{name}}!");
+    let context = || {
+        MapBuilder::new()
+            .str("name", "Mustafa")
+            .str("nickname", "Moiz")
+            .build()
+    };
+    assert_eq!(template.render(context()).unwrap(), "Hello Mustafa!");
+    Rename.visit_node_mut(template.ast_mut());
+    assert_eq!(template.render(context()).unwrap(), "Hello Moiz!");
+}
@@ -0,0 +1,106 @@
+use stache::ast::{Node, Span};
+use stache::{format, CompileOptions, Context, Error, MapBuilder, Template};
+
+mod macros;
+
+fn context() -> Context {
+    MapBuilder::new()
+        .str("a", "A")
+        .list("items", vec![Context::Integer(1), Context::Integer(2)])
+        .build()
+}
+
+mstest!(
+    verbatim,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{a}} {{{{raw}}}}<p>{{ message }}</p>{{#a}}{{/b}}{{{{/raw}}}} {{a}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    A <p>{{ message }}</p>{{#a}}{{/b}} A
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn single_text_node() {
+    let text = "{{{{ raw }}}}{{x}}\n{{y}}{{{{/raw}}}}";
+    let template = Template::compile(text).unwrap();
+    let children = template.ast().children();
+    assert_eq!(children.len(), 1);
+    let Node::Text(node) = &children[0] else {
+        panic!("expected text");
+    };
+    assert_eq!(node.text, "{{x}}\n{{y}}");
+    assert_eq!(node.span, Span::new(0, text.len()));
+}
+
+mstest!(
+    inside_sections,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#items}}
+    {{{{raw}}}}{{.}}{{{{/raw}}}}={{.}}
+    {{/items}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{.}}=1
+    {{.}}=2
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    names,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{{{go}}}}{{{{/raw}}}}{{ .Name }}{{{{/go}}}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{{{/raw}}}}{{ .Name }}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn delimiters() {
+    let text = "{{=<% %>=}}<%a%> {{{{raw}}}}<%a%>{{=| |=}}{{{{/raw}}}} <%a%> {{a}}";
+    assert_eq!(
+        macros::render(text, CompileOptions::new(), context()),
+        "A <%a%>{{=| |=}} A {{a}}"
+    );
+    let text = "{{{{raw}}}}{{=<% %>=}}{{{{/raw}}}}{{a}}<%a%>";
+    assert_eq!(
+        macros::render(text, CompileOptions::new(), context()),
+        "{{=<% %>=}}A<%a%>"
+    );
+}
+
+#[test]
+fn unclosed() {
+    for text in [
+        "{{{{raw}}}}{{a}}",
+        "{{{{raw}}}}{{a}}{{{{/other}}}}",
+        "{{{{raw",
+        "{{{{}}}}{{{{/}}}}",
+    ] {
+        assert!(
+            matches!(Template::compile(text), Err(Error::Parse { .. })),
+            "{text} should not compile"
+        );
+    }
+}
+
+#[test]
+fn whitespace_control() {
+    let options = CompileOptions::new().whitespace_control(true);
+    let text = "{{{{raw}}}} {{x}} {{{{/raw}}}} {{~a~}} {{{{raw}}}} {{{{/raw}}}}";
+    assert_eq!(macros::render(text, options, context()), " {{x}} A ");
+}
+
+#[test]
+fn formatting() {
+    let text = "{{#items}}\n{{{{raw}}}}{{ a }}\n  {{#b}}{{{{/raw}}}}\n{{/items}}\n";
+    let expected = "{{#items}}\n{{{{raw}}}}{{ a }}\n  {{#b}}{{{{/raw}}}}\n{{/items}}\n";
+    assert_eq!(format(text).unwrap(), expected);
+}
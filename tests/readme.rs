@@ -20,7 +20,7 @@ fn readme() {
         .bool("in_ca", true)
         .build();
 
-    let rendered = template.render(context);
+    let rendered = template.render(context).unwrap();
 
     let expected = "
         Hello Mustafa
@@ -30,3 +30,26 @@ fn readme() {
 
     assert_eq!(rendered, expected);
 }
+
+#[test]
+fn readme_filters() {
+    use stache::{CompileOptions, Context, MapBuilder, RenderOptions, Template};
+
+    let text = "{{name | trim | shout}} won {{count}} prize{{count | pluralize}}";
+    let template =
+        Template::compile_with_options(text, CompileOptions::new().filters(true)).unwrap();
+
+    let options = RenderOptions::new().filter("shout", |value, _| match value {
+        Some(Context::String(text)) => Ok(Some(Context::String(text.to_uppercase() + "!"))),
+        _ => Err("expected a string".into()),
+    });
+    let context = MapBuilder::new()
+        .str("name", " Mustafa ")
+        .int("count", 2)
+        .build();
+    let rendered = template
+        .render_with_options(context, Default::default(), options)
+        .unwrap();
+
+    assert_eq!(rendered, "MUSTAFA! won 2 prizes");
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use stache::{CompileOptions, Position, Reference, ReferenceKind, Result, Template};
+
+fn reference(
+    kind: ReferenceKind,
+    name: &str,
+    path: &[&str],
+    partial: Option<&str>,
+    (line, column): (usize, usize),
+) -> Reference {
+    Reference {
+        kind,
+        name: name.into(),
+        path: path.iter().map(|x| x.to_string()).collect(),
+        partial: partial.map(String::from),
+        position: Position { line, column },
+    }
+}
+
+#[test]
+fn variables() -> Result<()> {
+    let template = Template::compile("Hello {{name}}!\n{{{html}}} {{& raw }}")?;
+    let expected = vec![
+        reference(
+            ReferenceKind::Variable { escaped: true },
+            "name",
+            &[],
+            None,
+            (1, 7),
+        ),
+        reference(
+            ReferenceKind::Variable { escaped: false },
+            "html",
+            &[],
+            None,
+            (2, 1),
+        ),
+        reference(
+            ReferenceKind::Variable { escaped: false },
+            "raw",
+            &[],
+            None,
+            (2, 12),
+        ),
+    ];
+    assert_eq!(template.references()?, expected);
+    Ok(())
+}
+
+#[test]
+fn sections() -> Result<()> {
+    let text = "{{#repo}}\n  {{^empty}}{{name.first}}{{/empty}}\n{{/repo}}";
+    let template = Template::compile(text)?;
+    let expected = vec![
+        reference(ReferenceKind::Section, "repo", &[], None, (1, 1)),
+        reference(
+            ReferenceKind::InvertedSection,
+            "empty",
+            &["repo"],
+            None,
+            (2, 3),
+        ),
+        reference(
+            ReferenceKind::Variable { escaped: true },
+            "name.first",
+            &["repo", "empty"],
+            None,
+            (2, 13),
+        ),
+    ];
+    assert_eq!(template.references()?, expected);
+    Ok(())
+}
+
+#[test]
+fn partials_not_followed() -> Result<()> {
+    let template = Template::compile("{{#items}}{{>item}}{{/items}}")?;
+    let expected = vec![
+        reference(ReferenceKind::Section, "items", &[], None, (1, 1)),
+        reference(ReferenceKind::Partial, "item", &["items"], None, (1, 11)),
+    ];
+    assert_eq!(template.references()?, expected);
+    Ok(())
+}
+
+#[test]
+fn partials_followed() -> Result<()> {
+    let template = Template::compile("{{#nodes}}{{>node}}{{/nodes}}")?;
+    let partials = HashMap::from([(
+        String::from("node"),
+        String::from("{{content}}\n{{#nodes}}{{>node}}{{/nodes}}"),
+    )]);
+    let expected = vec![
+        reference(ReferenceKind::Section, "nodes", &[], None, (1, 1)),
+        reference(ReferenceKind::Partial, "node", &["nodes"], None, (1, 11)),
+        reference(
+            ReferenceKind::Variable { escaped: true },
+            "content",
+            &["nodes"],
+            Some("node"),
+            (1, 1),
+        ),
+        reference(
+            ReferenceKind::Section,
+            "nodes",
+            &["nodes"],
+            Some("node"),
+            (2, 1),
+        ),
+        reference(
+            ReferenceKind::Partial,
+            "node",
+            &["nodes", "nodes"],
+            Some("node"),
+            (2, 11),
+        ),
+    ];
+    assert_eq!(template.references_with_partials(&partials)?, expected);
+    Ok(())
+}
+
+#[test]
+fn helpers() -> Result<()> {
+    let options = CompileOptions::new().helpers(true);
+    let text = "{{#order}}{{#if_eq status \"paid\"}}{{amount}}{{/if_eq}}{{/order}}";
+    let template = Template::compile_with_options(text, options)?;
+    let expected = vec![
+        reference(ReferenceKind::Section, "order", &[], None, (1, 1)),
+        reference(
+            ReferenceKind::Variable { escaped: true },
+            "status",
+            &["order"],
+            None,
+            (1, 11),
+        ),
+        reference(
+            ReferenceKind::Variable { escaped: true },
+            "amount",
+            &["order"],
+            None,
+            (1, 35),
+        ),
+    ];
+    assert_eq!(template.references()?, expected);
+    Ok(())
+}
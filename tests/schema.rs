@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use stache::{
+    CompileOptions, Context, Issue, IssueKind, MapBuilder, Position, Result, Schema, Template,
+};
+
+fn issue(
+    kind: IssueKind,
+    name: &str,
+    partial: Option<&str>,
+    (line, column): (usize, usize),
+) -> Issue {
+    Issue {
+        kind,
+        name: name.into(),
+        partial: partial.map(String::from),
+        position: Position { line, column },
+    }
+}
+
+fn schema() -> Schema {
+    Schema::map([
+        ("title", Schema::Scalar),
+        (
+            "author",
+            Schema::map([("name", Schema::Scalar), ("email", Schema::Scalar)]),
+        ),
+        (
+            "posts",
+            Schema::list(Schema::map([
+                ("title", Schema::Scalar),
+                ("tags", Schema::list(Schema::Scalar)),
+            ])),
+        ),
+    ])
+}
+
+#[test]
+fn valid() -> Result<()> {
+    let text = "
+        {{title}} by {{author.name}}
+        {{#author}}{{email}}{{/author}}
+        {{#posts}}
+          {{title}} in {{author.name}}: {{#tags}}{{.}}{{/tags}}
+        {{/posts}}
+        {{^posts}}No posts{{/posts}}
+    ";
+    let template = Template::compile(text)?;
+    assert_eq!(template.check(&schema())?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn unknown() -> Result<()> {
+    let text = "{{subtitle}}\n{{author.phone}}\n{{#posts}}{{body}}{{/posts}}";
+    let template = Template::compile(text)?;
+    let expected = vec![
+        issue(IssueKind::Unknown, "subtitle", None, (1, 1)),
+        issue(IssueKind::Unknown, "author.phone", None, (2, 1)),
+        issue(IssueKind::Unknown, "body", None, (3, 11)),
+    ];
+    assert_eq!(template.check(&schema())?, expected);
+    Ok(())
+}
+
+#[test]
+fn not_a_map() -> Result<()> {
+    let template = Template::compile("{{title.length}} {{posts.title}}")?;
+    let expected = vec![
+        issue(IssueKind::NotAMap, "title.length", None, (1, 1)),
+        issue(IssueKind::NotAMap, "posts.title", None, (1, 18)),
+    ];
+    assert_eq!(template.check(&schema())?, expected);
+    Ok(())
+}
+
+#[test]
+fn not_a_scalar() -> Result<()> {
+    let template = Template::compile("{{author}} {{#posts}}{{.}}{{/posts}}")?;
+    let expected = vec![
+        issue(IssueKind::NotAScalar, "author", None, (1, 1)),
+        issue(IssueKind::NotAScalar, ".", None, (1, 22)),
+    ];
+    assert_eq!(template.check(&schema())?, expected);
+    Ok(())
+}
+
+#[test]
+fn not_a_list() -> Result<()> {
+    let text =
+        "{{#title}}{{.}}{{/title}} {{^title}}none{{/title}} {{#posts}}{{#tags}}{{/tags}}{{/posts}}";
+    let template = Template::compile(text)?;
+    let expected = vec![issue(IssueKind::NotAList, "title", None, (1, 1))];
+    assert_eq!(template.check(&schema())?, expected);
+    assert_eq!(
+        expected[0].to_string(),
+        "1:1: `title` opens a section on a scalar"
+    );
+    Ok(())
+}
+
+#[test]
+fn helper_bodies() -> Result<()> {
+    let options = CompileOptions::new().helpers(true);
+    let text = "{{#if_eq title \"x\"}}{{amount}}{{>post}}{{/if_eq}}";
+    let template = Template::compile_with_options(text, options)?;
+    let partials = HashMap::from([(String::from("post"), String::from("{{date}}"))]);
+    let expected = vec![
+        issue(IssueKind::Unknown, "amount", None, (1, 21)),
+        issue(IssueKind::Unknown, "date", Some("post"), (1, 1)),
+    ];
+    assert_eq!(
+        template.check_with_partials(&schema(), &partials)?,
+        expected
+    );
+    Ok(())
+}
+
+#[test]
+fn anchored_dot() -> Result<()> {
+    let text = "{{%ANCHORED-DOT}}{{#posts}}{{.title}} {{.author}}{{/posts}}";
+    let template = Template::compile(text)?;
+    let expected = vec![issue(IssueKind::Unknown, ".author", None, (1, 39))];
+    assert_eq!(template.check(&schema())?, expected);
+    Ok(())
+}
+
+#[test]
+fn partials() -> Result<()> {
+    let template = Template::compile("{{#posts}}{{>post}}{{/posts}}")?;
+    let partials = HashMap::from([(String::from("post"), String::from("{{title}}\n{{date}}"))]);
+    let expected = vec![issue(IssueKind::Unknown, "date", Some("post"), (2, 1))];
+    assert_eq!(
+        template.check_with_partials(&schema(), &partials)?,
+        expected
+    );
+    Ok(())
+}
+
+#[test]
+fn from_context() {
+    let context = MapBuilder::new()
+        .str("title", "Hello")
+        .list(
+            "posts",
+            vec![
+                MapBuilder::new().str("title", "First").build(),
+                MapBuilder::new()
+                    .list("tags", vec![Context::String("rust".into())])
+                    .build(),
+            ],
+        )
+        .build();
+    let expected = Schema::map([
+        ("title", Schema::Scalar),
+        (
+            "posts",
+            Schema::list(Schema::map([
+                ("title", Schema::Scalar),
+                ("tags", Schema::list(Schema::Scalar)),
+            ])),
+        ),
+    ]);
+    assert_eq!(Schema::from_context(&context), expected);
+}
+
+#[test]
+fn from_json_schema() {
+    let document = Context::Map(HashMap::from([
+        ("type".into(), Context::String("object".into())),
+        (
+            "properties".into(),
+            Context::Map(HashMap::from([
+                (
+                    "title".into(),
+                    Context::Map(HashMap::from([(
+                        "type".into(),
+                        Context::String("string".into()),
+                    )])),
+                ),
+                (
+                    "tags".into(),
+                    Context::Map(HashMap::from([
+                        ("type".into(), Context::String("array".into())),
+                        (
+                            "items".into(),
+                            Context::Map(HashMap::from([(
+                                "type".into(),
+                                Context::String("string".into()),
+                            )])),
+                        ),
+                    ])),
+                ),
+                ("extra".into(), Context::Map(HashMap::new())),
+            ])),
+        ),
+    ]));
+    let expected = Schema::map([
+        ("title", Schema::Scalar),
+        ("tags", Schema::list(Schema::Scalar)),
+        ("extra", Schema::Any),
+    ]);
+    assert_eq!(Schema::from_json_schema(&document), expected);
+}
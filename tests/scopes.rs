@@ -0,0 +1,161 @@
+use stache::ast::Name;
+use stache::{
+    CompileOptions, Context, Error, MapBuilder, Partials, ReferenceKind, RenderOptions, Template,
+};
+
+mod macros;
+
+fn context() -> Context {
+    let pet = |name: &str| MapBuilder::new().str("name", name).build();
+    let person = |name: &str, pets: Vec<Context>| {
+        MapBuilder::new()
+            .str("name", name)
+            .list("pets", pets)
+            .build()
+    };
+    MapBuilder::new()
+        .str("name", "Registry")
+        .str("layout", "card")
+        .list(
+            "people",
+            vec![
+                person("Ada", vec![pet("Rex"), pet("Tom")]),
+                person("Bob", vec![pet("Kit")]),
+            ],
+        )
+        .build()
+}
+
+#[test]
+fn parse() {
+    let name = |level, inner| Name::Parent(level, Box::new(inner));
+    assert_eq!(Name::parse("../name"), Some(name(1, Name::Key("name"))));
+    assert_eq!(
+        Name::parse("../../a.b"),
+        Some(name(2, Name::Path(vec!["a", "b"])))
+    );
+    assert_eq!(Name::parse(".."), Some(name(1, Name::Implicit)));
+    assert_eq!(Name::parse("../.."), Some(name(2, Name::Implicit)));
+    assert_eq!(
+        Name::parse("@root.name"),
+        Some(Name::Root(Box::new(Name::Key("name"))))
+    );
+    assert_eq!(
+        Name::parse("@root"),
+        Some(Name::Root(Box::new(Name::Implicit)))
+    );
+    assert_eq!(Name::parse("@rooted"), Some(Name::Key("@rooted")));
+    assert_eq!(Name::parse("../"), None);
+    assert_eq!(Name::parse("@root."), None);
+    for name in ["../name", "../../a.b", "..", "../..", "@root", "@root.name"] {
+        assert_eq!(Name::parse(name).unwrap().to_string(), name);
+    }
+    let anchored = |inner| Some(Name::Anchored(Box::new(inner)));
+    assert_eq!(Name::parse_anchored(".name"), anchored(Name::Key("name")));
+    assert_eq!(
+        Name::parse_anchored(".a.b"),
+        anchored(Name::Path(vec!["a", "b"]))
+    );
+    assert_eq!(Name::parse_anchored("."), Some(Name::Implicit));
+    assert_eq!(Name::parse_anchored(".."), Some(name(1, Name::Implicit)));
+    assert_eq!(Name::parse_anchored(".a."), None);
+    assert_eq!(Name::parse(".name"), None);
+    assert_eq!(Name::parse_anchored(".a.b").unwrap().to_string(), ".a.b");
+}
+
+mstest!(
+    parent,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#people}}{{#pets}}{{name}} of {{../name}} in {{../../name}}; {{/pets}}{{/people}}|
+    {{#people}}{{#pets}}{{#..}}{{name}}{{/..}}{{/pets}}{{/people}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Rex of Ada in Registry; Tom of Ada in Registry; Kit of Bob in Registry; |
+    AdaAdaBob
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    root,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#people}}{{#pets}}{{@root.name}}/{{name}} {{/pets}}{{/people}}|
+    [{{#people}}{{@root.people.length}}{{/people}}]
+    {{#@root}}{{name}}{{/@root}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    Registry/Rex Registry/Tom Registry/Kit |
+    []
+    Registry
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+// A key missing from the parent is still found further out.
+mstest!(
+    skipped_contexts_fall_back_outward,
+    context(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {{#people}}{{#pets}}{{../layout}}{{/pets}}{{/people}}
+    {{#people}}[{{../pets}}]{{/people}}
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    cardcardcard
+    [][]
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+#[test]
+fn past_root() {
+    let error = macros::render_error(
+        "{{#people}}{{../../name}}{{/people}}",
+        CompileOptions::new(),
+        context(),
+    );
+    assert_eq!(error, Error::Scope("../../name".into()));
+    assert_eq!(
+        error.to_string(),
+        "`../../name` walks past the root context"
+    );
+    assert_eq!(
+        macros::render_error(
+            "{{#../flag}}x{{/../flag}}",
+            CompileOptions::new(),
+            context()
+        ),
+        Error::Scope("../flag".into())
+    );
+}
+
+#[test]
+fn dynamic_partials() {
+    let partials = Partials::from([
+        ("card".to_string(), "<{{name}}>".to_string()),
+        ("row".to_string(), "{{name}},".to_string()),
+    ]);
+    let render_options = RenderOptions::new();
+    for (text, expected) in [
+        (
+            "{{>*layout}} {{#people}}{{>*@root.layout}}{{/people}}",
+            "<Registry> <Ada><Bob>",
+        ),
+        ("[{{>*missing}}{{>*people}}]", "[]"),
+    ] {
+        let options = CompileOptions::new();
+        let rendered =
+            macros::render_backends(text, options, context(), &partials, &render_options);
+        assert_eq!(rendered.unwrap(), expected);
+    }
+}
+
+#[test]
+fn references() {
+    let template = Template::compile("{{#people}}{{../name}}{{>*layout}}{{/people}}").unwrap();
+    let references = template.references().unwrap();
+    assert_eq!(references[1].name, "../name");
+    assert_eq!(references[2].name, "layout");
+    assert_eq!(references[2].kind, ReferenceKind::DynamicPartial);
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use stache::{
+    Block, CompileOptions, Context, MapBuilder, Partials, RenderOptions, Template, Truthiness,
+};
+
+mod macros;
+
+/// Renders `yes` or `no` for each value in the context, in key order.
+fn render(truthiness: Truthiness) -> String {
+    let text = "{{#values}}{{#value}}yes{{/value}}{{^value}}no{{/value}} {{/values}}";
+    let values = [
+        Context::String("".into()),
+        Context::String("0".into()),
+        Context::Integer(0),
+        Context::Integer(2),
+        Context::Float(0.0),
+        Context::Float(f64::NAN),
+        Context::Map(HashMap::new()),
+        Context::List(Vec::new()),
+        Context::Null,
+    ];
+    let values = values.map(|value| Context::Map(HashMap::from([("value".to_string(), value)])));
+    let context = MapBuilder::new().list("values", values.to_vec()).build();
+    let options = RenderOptions::new().truthiness(truthiness);
+    macros::render_backends(
+        text,
+        CompileOptions::new(),
+        context,
+        &Partials::new(),
+        &options,
+    )
+    .unwrap()
+}
+
+#[test]
+fn spec() {
+    assert_eq!(
+        render(Truthiness::Spec),
+        "yes yes yes yes yes yes yes no no "
+    );
+    assert_eq!(render(Truthiness::default()), render(Truthiness::Spec));
+}
+
+#[test]
+fn javascript() {
+    assert_eq!(
+        render(Truthiness::JavaScript),
+        "no yes no yes no no yes no no "
+    );
+}
+
+#[test]
+fn python() {
+    assert_eq!(render(Truthiness::Python), "no yes no yes no yes no no no ");
+}
+
+#[test]
+fn custom() {
+    let truthiness =
+        Truthiness::custom(|value| matches!(value, Context::Integer(number) if *number > 0));
+    assert_eq!(render(truthiness), "no no no yes no no no no no ");
+}
+
+#[test]
+fn missing() {
+    let truthiness = Truthiness::custom(|_| true);
+    let template =
+        Template::compile("{{#missing}}yes{{/missing}}{{^missing}}no{{/missing}}").unwrap();
+    let options = RenderOptions::new().truthiness(truthiness);
+    let output =
+        template.render_with_options(MapBuilder::new().build(), Default::default(), options);
+    assert_eq!(output.unwrap(), "no");
+}
+
+#[test]
+fn helpers() {
+    let options = CompileOptions::new().helpers(true);
+    let template = Template::compile_with_options(
+        "{{#with count}}[{{.}}]{{/with}}{{#truthy count}}{{/truthy}}",
+        options,
+    )
+    .unwrap();
+    let context = || MapBuilder::new().int("count", 0).build();
+    let render = |truthiness| {
+        let options =
+            RenderOptions::new()
+                .truthiness(truthiness)
+                .helper("truthy", |block: &mut Block| {
+                    let truthy = block.arg(0).is_some_and(|value| block.is_truthy(value));
+                    block.write(&truthy.to_string())
+                });
+        template
+            .render_with_options(context(), Default::default(), options)
+            .unwrap()
+    };
+    assert_eq!(render(Truthiness::Spec), "[0]true");
+    assert_eq!(render(Truthiness::JavaScript), "false");
+}
@@ -7,7 +7,7 @@ fn preserve_inner() -> Result<()> {
     let text = "Hello world";
     let template = Template::compile(text)?;
     let context = Context::Map(HashMap::new());
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
     let expected = "Hello world";
     assert_eq!(rendered, expected);
     Ok(())
@@ -18,7 +18,7 @@ fn preserve_indent() -> Result<()> {
     let text = "   Hello world";
     let template = Template::compile(text)?;
     let context = Context::Map(HashMap::new());
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
     let expected = "   Hello world";
     assert_eq!(rendered, expected);
     Ok(())
@@ -29,7 +29,7 @@ fn preserve_trailing() -> Result<()> {
     let text = "Hello world   ";
     let template = Template::compile(text)?;
     let context = Context::Map(HashMap::new());
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
     let expected = "Hello world   ";
     assert_eq!(rendered, expected);
     Ok(())
@@ -47,7 +47,7 @@ fn preserve_newlines() -> Result<()> {
 
     let template = Template::compile(text)?;
     let context = Context::Map(HashMap::new());
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = indoc! {"
         Hello world
@@ -73,7 +73,7 @@ fn preserve_standalone_variable() -> Result<()> {
         String::from("greeting"),
         Context::String("Goodbye world".into()),
     )]));
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = indoc! {"
         Hello world
@@ -93,7 +93,7 @@ fn strip_standalone_special_tag() -> Result<()> {
 
     let template = Template::compile(text)?;
     let context = Context::Map(HashMap::new());
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = indoc! {"
         Hello world
@@ -116,7 +116,7 @@ fn strip_standalone_special_tags_2() -> Result<()> {
         String::from("condition"),
         Context::Bool(true),
     )]));
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = indoc! {"
         Hello world
@@ -141,7 +141,7 @@ fn strip_standalone_special_tags_3() -> Result<()> {
         (String::from("condition"), Context::Bool(true)),
         (String::from("condition2"), Context::Bool(true)),
     ]));
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = indoc! {"
         Hello world
@@ -160,7 +160,7 @@ fn strip_standalone_special_tags_4() -> Result<()> {
 
     let template = Template::compile(text)?;
     let context = Context::Map(HashMap::new());
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = indoc! {"
         Hello world
@@ -181,7 +181,7 @@ fn preserve_multiple_special_tags() -> Result<()> {
         String::from("condition"),
         Context::Bool(true),
     )]));
-    let rendered = template.render(context);
+    let rendered = template.render(context)?;
 
     let expected = "   \n";
 
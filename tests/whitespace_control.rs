@@ -0,0 +1,123 @@
+use stache::{CompileOptions, Context, Error, MapBuilder, Partials, RenderOptions, Template};
+
+mod macros;
+
+fn options() -> CompileOptions {
+    CompileOptions::new().whitespace_control(true)
+}
+
+fn items() -> Context {
+    let items = ["a", "b", "c"].map(|name| MapBuilder::new().str("name", name).build());
+    MapBuilder::new().list("items", items.to_vec()).build()
+}
+
+#[test]
+fn variables() {
+    let context = || MapBuilder::new().str("name", "Ada").build();
+    for (text, expected) in [
+        ("[ {{~name}} ]", "[Ada ]"),
+        ("[ {{name~}} ]", "[ Ada]"),
+        ("[ \n\t {{~ name ~}} \r\n ]", "[Ada]"),
+        ("[ {{~& name ~}} ]", "[Ada]"),
+        ("[ {{~{name}~}} ]", "[Ada]"),
+        ("[ {{~{name}}} ]", "[Ada ]"),
+    ] {
+        assert_eq!(macros::render(text, options(), context()), expected);
+    }
+}
+
+mstest!(
+    sections,
+    options = options(),
+    items(),
+    "
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    [
+      {{~#items~}}
+        {{name}}
+      {{~^last}},{{/last~}}
+      {{~/items~}}
+    ]
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    [a,b,c,]
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "
+);
+
+mstest!(
+    compact_json,
+    options = options(),
+    items(),
+    r#"
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {
+      "items": [
+        {{~#items~}}
+        "{{name}}"{{^last}},{{/last}}
+        {{~/items~}}
+      ]
+    }
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    {
+      "items": ["a","b","c",]
+    }
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "#
+);
+
+#[test]
+fn comments() {
+    let context = || MapBuilder::new().build();
+    assert_eq!(
+        macros::render("a\n\n  {{~! note ~}}\n\n  b", options(), context()),
+        "ab"
+    );
+    assert_eq!(
+        macros::render("a {{! note ~}} b", options(), context()),
+        "a b"
+    );
+}
+
+#[test]
+fn partials() {
+    let partials = Partials::from([("item".to_string(), "<{{name}}>".to_string())]);
+    let text = "{{#items}}\n  {{~>item~}}\n{{/items}}";
+    let rendered =
+        macros::render_backends(text, options(), items(), &partials, &RenderOptions::new());
+    assert_eq!(rendered.unwrap(), "<a><b><c>");
+}
+
+#[test]
+fn only_whitespace() {
+    let context = || MapBuilder::new().str("name", "Ada").build();
+    for (text, expected) in [
+        ("a b  {{~name~}}  c d", "a bAdac d"),
+        ("{{~name~}}", "Ada"),
+        (" x y \n {{~name}}", " x yAda"),
+    ] {
+        assert_eq!(macros::render(text, options(), context()), expected);
+    }
+}
+
+#[test]
+fn custom_delimiters() {
+    let context = || MapBuilder::new().str("name", "Ada").build();
+    let text = "{{=<% %>=}} [ <%~name~%> ] {{~name~}} <%={{ }}=%> [ {{~name}} ]";
+    assert_eq!(
+        macros::render(text, options(), context()),
+        " [Ada] {{~name~}}  [Ada ]"
+    );
+    let text = "{{=| |=}}( |~{name}~| )";
+    assert_eq!(macros::render(text, options(), context()), "(Ada)");
+}
+
+#[test]
+fn disabled_by_default() {
+    let template = Template::compile("{{~name~}}").unwrap();
+    let context = MapBuilder::new().str("~name~", "tilde").build();
+    assert_eq!(template.render(context).unwrap(), "tilde");
+    assert!(matches!(
+        Template::compile("{{#a~}}{{/a}}"),
+        Err(Error::Parse { .. })
+    ));
+}